@@ -0,0 +1,402 @@
+//! serde deserialization straight from the parser events.
+//!
+//! strings are borrowed from the input when deserializing from a slice and
+//! the string contains no escape.
+
+use std::fmt;
+use std::io::Read;
+use std::str;
+use serde::de::{self, DeserializeOwned, DeserializeSeed, Visitor};
+use event::{parse_int, parse_uint};
+use {Config, ErrorKind, EventReader, JError, JResult, Jev, Position, Span};
+
+impl de::Error for JError {
+    fn custom<T: fmt::Display>(msg: T) -> JError {
+        JError::custom(msg)
+    }
+}
+
+fn as_str(data: &[u8]) -> JResult<&str> {
+    str::from_utf8(data).map_err(|_| JError::new(ErrorKind::Utf8))
+}
+
+fn unexpected<T>() -> JResult<T> {
+    Err(JError::new(ErrorKind::UnexpectedEvent))
+}
+
+/// serde deserializer pulling events from the parser
+pub struct Deserializer<'de, R: Read> {
+    reader: EventReader<R>,
+    input: Option<&'de [u8]>,
+    peeked: Option<(Jev, Span)>,
+    scratch: Vec<u8>,
+    /* start of the last event read, where the errors without position are */
+    last: Option<Position>,
+}
+
+impl<'de, R: Read> Deserializer<'de, R> {
+    pub fn from_reader(config: Config, inp: R) -> Deserializer<'de, R> {
+        Deserializer { reader: EventReader::new(config, inp), input: None, peeked: None, scratch: vec![], last: None }
+    }
+
+    /// check that the whole input has been consumed
+    pub fn end(&mut self) -> JResult<()> {
+        match self.peek()? {
+            None    => Ok(()),
+            Some(_) => unexpected(),
+        }
+    }
+
+    fn peek(&mut self) -> JResult<Option<Jev>> {
+        while self.peeked.is_none() {
+            match self.reader.next_raw()? {
+                None => return Ok(None),
+                Some(ref ev) if ev.jev == Jev::Comment || ev.jev == Jev::DocumentEnd => (),
+                Some(ref ev) if ev.jev == Jev::Error => {
+                    return Err(JError::custom(as_str(ev.data.unwrap_or(&[]))?).at(ev.span.start))
+                },
+                Some(ev) => {
+                    self.scratch.clear();
+                    if let Some(d) = ev.data {
+                        self.scratch.extend_from_slice(d);
+                    }
+                    self.peeked = Some((ev.jev, ev.span));
+                    self.last = Some(ev.span.start);
+                },
+            }
+        }
+        Ok(self.peeked.map(|p| p.0))
+    }
+
+    fn next(&mut self) -> JResult<(Jev, Span)> {
+        self.peek()?;
+        self.peeked.take().ok_or_else(|| JError::new(ErrorKind::PrematureEof(None)))
+    }
+
+    fn expect(&mut self, jev: Jev) -> JResult<()> {
+        if self.next()?.0 == jev { Ok(()) } else { unexpected() }
+    }
+
+    /* visit the string of the last event, borrowed from the input if possible */
+    fn visit_string<V: Visitor<'de>>(&self, span: Span, visitor: V) -> JResult<V::Value> {
+        if let Some(input) = self.input {
+            // JSON5 identifier keys have no quotes
+            let raw = &input[span.start.offset..span.end.offset];
+            let raw = match raw.first() {
+                Some(&b'"') | Some(&b'\'') => &raw[1..raw.len() - 1],
+                _ => raw,
+            };
+            if !raw.contains(&b'\\') {
+                return visitor.visit_borrowed_str(as_str(raw)?);
+            }
+        }
+        visitor.visit_str(as_str(&self.scratch)?)
+    }
+
+    fn visit_number<V: Visitor<'de>>(&self, jev: Jev, visitor: V) -> JResult<V::Value> {
+        let s = as_str(&self.scratch)?;
+        if jev == Jev::Int {
+            if let Some(i) = parse_int(s) {
+                return visitor.visit_i64(i);
+            }
+            if let Some(u) = parse_uint(s) {
+                return visitor.visit_u64(u);
+            }
+        }
+        match s.parse::<f64>() {
+            Ok(f)  => visitor.visit_f64(f),
+            Err(_) => Err(JError::new(ErrorKind::InvalidValue)),
+        }
+    }
+}
+
+impl<'de> Deserializer<'de, &'de [u8]> {
+    pub fn from_slice(config: Config, input: &'de [u8]) -> Deserializer<'de, &'de [u8]> {
+        Deserializer {
+            reader: EventReader::new(config, input),
+            input: Some(input),
+            peeked: None,
+            scratch: vec![],
+            last: None,
+        }
+    }
+}
+
+fn deserialize_all<'de, R: Read, T: de::Deserialize<'de>>(mut de: Deserializer<'de, R>) -> JResult<T> {
+    let r = T::deserialize(&mut de).and_then(|v| de.end().map(|_| v));
+    r.map_err(|e| {
+        let pos = de.last.unwrap_or_else(|| de.reader.position());
+        e.at(pos)
+    })
+}
+
+/// deserialize a value from a whole document in memory
+pub fn from_slice<'de, T: de::Deserialize<'de>>(config: Config, input: &'de [u8]) -> JResult<T> {
+    deserialize_all(Deserializer::from_slice(config, input))
+}
+
+/// deserialize a value from a whole document in a string
+pub fn from_str<'de, T: de::Deserialize<'de>>(config: Config, input: &'de str) -> JResult<T> {
+    from_slice(config, input.as_bytes())
+}
+
+/// deserialize a value from a whole document read from a reader
+pub fn from_reader<R: Read, T: DeserializeOwned>(config: Config, inp: R) -> JResult<T> {
+    deserialize_all(Deserializer::from_reader(config, inp))
+}
+
+impl<'de, R: Read> de::Deserializer<'de> for &mut Deserializer<'de, R> {
+    type Error = JError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> JResult<V::Value> {
+        let (jev, span) = self.next()?;
+        match jev {
+            Jev::ArrayStart  => {
+                let v = visitor.visit_seq(SeqAccess { de: self })?;
+                self.expect(Jev::ArrayEnd)?;
+                Ok(v)
+            },
+            Jev::ObjectStart => {
+                let v = visitor.visit_map(MapAccess { de: self })?;
+                self.expect(Jev::ObjectEnd)?;
+                Ok(v)
+            },
+            Jev::String      => self.visit_string(span, visitor),
+            Jev::Int | Jev::Float => self.visit_number(jev, visitor),
+            Jev::True        => visitor.visit_bool(true),
+            Jev::False       => visitor.visit_bool(false),
+            Jev::Null        => visitor.visit_unit(),
+            _                => unexpected(),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> JResult<V::Value> {
+        if self.peek()? == Some(Jev::Null) {
+            self.next()?;
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> JResult<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V)
+        -> JResult<V::Value> {
+        match self.peek()? {
+            Some(Jev::String)      => visitor.visit_enum(EnumAccess { de: self, unit: true }),
+            Some(Jev::ObjectStart) => {
+                self.next()?;
+                let v = visitor.visit_enum(EnumAccess { de: self, unit: false })?;
+                self.expect(Jev::ObjectEnd)?;
+                Ok(v)
+            },
+            _                      => unexpected(),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+struct SeqAccess<'a, 'de: 'a, R: Read + 'a> {
+    de: &'a mut Deserializer<'de, R>,
+}
+
+impl<'de, 'a, R: Read> de::SeqAccess<'de> for SeqAccess<'a, 'de, R> {
+    type Error = JError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> JResult<Option<T::Value>> {
+        match self.de.peek()? {
+            Some(Jev::ArrayEnd) => Ok(None),
+            _                   => seed.deserialize(&mut *self.de).map(Some),
+        }
+    }
+}
+
+struct MapAccess<'a, 'de: 'a, R: Read + 'a> {
+    de: &'a mut Deserializer<'de, R>,
+}
+
+impl<'de, 'a, R: Read> de::MapAccess<'de> for MapAccess<'a, 'de, R> {
+    type Error = JError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> JResult<Option<K::Value>> {
+        match self.de.peek()? {
+            Some(Jev::ObjectEnd) => Ok(None),
+            Some(Jev::Key)       => seed.deserialize(KeyDeserializer { de: &mut *self.de }).map(Some),
+            _                    => unexpected(),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> JResult<V::Value> {
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+/* deserializer for the key event at the head of the stream */
+struct KeyDeserializer<'a, 'de: 'a, R: Read + 'a> {
+    de: &'a mut Deserializer<'de, R>,
+}
+
+impl<'de, 'a, R: Read> de::Deserializer<'de> for KeyDeserializer<'a, 'de, R> {
+    type Error = JError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> JResult<V::Value> {
+        let (_, span) = self.de.next()?;
+        self.de.visit_string(span, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+/* externally tagged enum: either "Variant" or {"Variant": value} */
+struct EnumAccess<'a, 'de: 'a, R: Read + 'a> {
+    de: &'a mut Deserializer<'de, R>,
+    unit: bool,
+}
+
+impl<'de, 'a, R: Read> de::EnumAccess<'de> for EnumAccess<'a, 'de, R> {
+    type Error = JError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> JResult<(V::Value, Self)> {
+        let v =
+            if self.unit {
+                seed.deserialize(&mut *self.de)?
+            } else {
+                if self.de.peek()? != Some(Jev::Key) {
+                    return unexpected();
+                }
+                seed.deserialize(KeyDeserializer { de: &mut *self.de })?
+            };
+        Ok((v, self))
+    }
+}
+
+impl<'de, 'a, R: Read> de::VariantAccess<'de> for EnumAccess<'a, 'de, R> {
+    type Error = JError;
+
+    fn unit_variant(self) -> JResult<()> {
+        if self.unit {
+            Ok(())
+        } else {
+            de::Deserialize::deserialize(self.de)
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> JResult<T::Value> {
+        if self.unit {
+            return Err(de::Error::invalid_type(de::Unexpected::UnitVariant, &"newtype variant"));
+        }
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> JResult<V::Value> {
+        if self.unit {
+            return Err(de::Error::invalid_type(de::Unexpected::UnitVariant, &"tuple variant"));
+        }
+        de::Deserializer::deserialize_seq(self.de, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, _fields: &'static [&'static str], visitor: V) -> JResult<V::Value> {
+        if self.unit {
+            return Err(de::Error::invalid_type(de::Unexpected::UnitVariant, &"struct variant"));
+        }
+        de::Deserializer::deserialize_map(self.de, visitor)
+    }
+}
+
+#[cfg(test)]
+use std::borrow::Cow;
+#[cfg(test)]
+use std::collections::BTreeMap;
+
+#[cfg(test)]
+#[derive(Deserialize, PartialEq, Debug)]
+enum Shape {
+    Empty,
+    Circle(f64),
+    Point(i32, i32),
+    Rect { w: u32, h: u32 },
+}
+
+#[cfg(test)]
+#[derive(Deserialize, PartialEq, Debug)]
+struct Item<'a> {
+    name: &'a str,
+    #[serde(borrow)]
+    label: Cow<'a, str>,
+    count: Option<u64>,
+    tags: Vec<String>,
+    shapes: Vec<Shape>,
+    extra: BTreeMap<String, i64>,
+}
+
+#[test]
+fn deserialize_struct() {
+    let doc = r#"{
+        "name": "first", "label": "lbl", "count": null, "tags": ["a", "b"],
+        "ignored": {"x": [1, 2, {"y": null}]},
+        "shapes": ["Empty", {"Circle": 1.5}, {"Point": [1, -2]}, {"Rect": {"w": 3, "h": 4}}],
+        "extra": {"k": -7}
+    }"#;
+    let item : Item = from_str(Config::default(), doc).ok().unwrap();
+    assert!(item.name == "first");
+    match item.label {
+        Cow::Borrowed(l) => assert!(l == "lbl"),
+        Cow::Owned(_) => panic!("expected borrowed label"),
+    }
+    assert!(item.count.is_none());
+    assert!(item.tags == vec!["a", "b"]);
+    assert!(item.shapes == vec![Shape::Empty, Shape::Circle(1.5), Shape::Point(1, -2), Shape::Rect { w: 3, h: 4 }]);
+    assert!(item.extra.get("k") == Some(&-7));
+}
+
+#[test]
+fn deserialize_json5_strings() {
+    let config = Config::default().allow_json5(true);
+    let m : BTreeMap<&str, &str> = from_str(config.clone(), "{abc: 'x\"y', $d_1: \"z\", 'q': ''}").ok().unwrap();
+    assert!(m.into_iter().collect::<Vec<_>>() == vec![("$d_1", "z"), ("abc", "x\"y"), ("q", "")]);
+    let m : BTreeMap<String, String> = from_str(config, "{k: 'a\\'b', l: \"c\\\nd\"}").ok().unwrap();
+    assert!(m.get("k").map(|s| &s[..]) == Some("a'b") && m.get("l").map(|s| &s[..]) == Some("cd"));
+}
+
+#[test]
+fn deserialize_json5_numbers() {
+    let config = Config::default().allow_json5(true);
+    let v : (u8, i32, i64, u64, f64, f64) = from_str(config, "[0x1F, -0x10, +7, 0xFFFFFFFFFFFFFFFF, .5, -Infinity]").ok().unwrap();
+    assert!(v == (31, -16, 7, u64::MAX, 0.5, f64::NEG_INFINITY));
+}
+
+#[test]
+fn deserialize_owned_and_errors() {
+    let v : Vec<(u8, bool, Option<String>)> =
+        from_reader(Config::default(), &b"[[1, true, \"x\"], [2, false, null]]"[..]).ok().unwrap();
+    assert!(v == vec![(1, true, Some("x".to_string())), (2, false, None)]);
+
+    let r : Result<u8, _> = from_str(Config::default(), "256");
+    assert!(r.err().map(|e| e.kind()) == Some(ErrorKind::Custom));
+    let r : Result<Vec<u8>, _> = from_str(Config::default(), "[1, 2");
+    assert!(r.err().map(|e| e.kind()) == Some(ErrorKind::PrematureEof(Some(::StackMode::Array))));
+    let r : Result<(u8,), _> = from_str(Config::default(), "[1, 2]");
+    assert!(r.err().map(|e| e.kind()) == Some(ErrorKind::UnexpectedEvent));
+
+    // errors found while deserializing are at the event that caused them
+    let pos = |r: JResult<Item>| r.err().and_then(|e| e.position()).map(|p| (p.line, p.column));
+    let doc = "{\"name\": \"n\", \"label\": \"l\", \"count\": 1,\n \"tags\": [\"a\",\n   2]}";
+    assert!(pos(from_str(Config::default(), doc)) == Some((3, 4)));
+    let doc = "{\"name\": \"n\",\n \"label\": \"l\"\n}";
+    assert!(pos(from_str(Config::default(), doc)) == Some((3, 1)));
+    let r : Result<u8, _> = from_str(Config::default(), "\n\n 256");
+    assert!(r.err().and_then(|e| e.position()) == Some(::pos(3, 3, 2)));
+}
@@ -0,0 +1,121 @@
+use std::str;
+use {ErrorKind, JError, JResult, Jev};
+
+/// JSON event with its decoded value.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Event<'a> {
+    ArrayStart,
+    ObjectStart,
+    ArrayEnd,
+    ObjectEnd,
+    Key(&'a str),
+    String(&'a str),
+    /// integer fitting in 64 bits
+    Int(i64),
+    /// integer too big for 64 bits, as written in the document
+    BigInt(&'a str),
+    Float(f64),
+    Bool(bool),
+    Null,
+    /// comment, with its delimiters
+    Comment(&'a str),
+    /// end of a document in multi document mode, with its index
+    DocumentEnd(usize),
+    /// error in the input recovered from, with `recover_errors` or in a
+    /// JSON text sequence, with its message
+    Error(&'a str),
+}
+
+fn as_str(data: Option<&[u8]>) -> JResult<&str> {
+    str::from_utf8(data.unwrap_or(&[])).map_err(|_| JError::new(ErrorKind::Utf8))
+}
+
+/* sign, digits and radix of a decimal integer, or JSON5 hexadecimal integer */
+fn split_int(s: &str) -> (bool, &str, u32) {
+    let (neg, digits) =
+        match s.as_bytes().first() {
+            Some(&b'-') => (true, &s[1..]),
+            Some(&b'+') => (false, &s[1..]),
+            _           => (false, s),
+        };
+    match digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        Some(h) => (neg, h, 16),
+        None    => (neg, digits, 10),
+    }
+}
+
+/// parse an integer as written in a document, None if it doesn't fit in
+/// an i64
+pub fn parse_int(s: &str) -> Option<i64> {
+    let (neg, digits, radix) = split_int(s);
+    let v = u64::from_str_radix(digits, radix).ok()?;
+    if neg {
+        0i64.checked_sub_unsigned(v)
+    } else {
+        0i64.checked_add_unsigned(v)
+    }
+}
+
+/// parse an integer as written in a document, None if it doesn't fit in
+/// an u64
+pub fn parse_uint(s: &str) -> Option<u64> {
+    match split_int(s) {
+        (false, digits, radix) => u64::from_str_radix(digits, radix).ok(),
+        (true, _, _)           => None,
+    }
+}
+
+impl<'a> Event<'a> {
+    /// decode a raw event and its data as generated by the parser
+    pub fn from_raw(jev: Jev, data: Option<&'a [u8]>) -> JResult<Event<'a>> {
+        let ev = match jev {
+            Jev::ArrayStart  => Event::ArrayStart,
+            Jev::ObjectStart => Event::ObjectStart,
+            Jev::ArrayEnd    => Event::ArrayEnd,
+            Jev::ObjectEnd   => Event::ObjectEnd,
+            Jev::Key         => Event::Key(as_str(data)?),
+            Jev::String      => Event::String(as_str(data)?),
+            Jev::Int         => {
+                let s = as_str(data)?;
+                match parse_int(s) {
+                    Some(i) => Event::Int(i),
+                    None    => Event::BigInt(s),
+                }
+            },
+            Jev::Float       => Event::Float(as_str(data)?.parse::<f64>().map_err(|_| JError::new(ErrorKind::UnexpectedChar))?),
+            Jev::True        => Event::Bool(true),
+            Jev::False       => Event::Bool(false),
+            Jev::Null        => Event::Null,
+            Jev::Comment     => Event::Comment(as_str(data)?),
+            Jev::Error       => Event::Error(as_str(data)?),
+            Jev::DocumentEnd => Event::DocumentEnd(as_str(data)?.parse::<usize>().map_err(|_| JError::new(ErrorKind::UnexpectedChar))?),
+        };
+        Ok(ev)
+    }
+}
+
+#[test]
+fn decode_values() {
+    assert!(Event::from_raw(Jev::Int, Some(b"-42")).ok() == Some(Event::Int(-42)));
+    assert!(Event::from_raw(Jev::Int, Some(b"123456789012345678901234567890")).ok()
+            == Some(Event::BigInt("123456789012345678901234567890")));
+    assert!(Event::from_raw(Jev::Float, Some(b"-1.5e3")).ok() == Some(Event::Float(-1500.0)));
+    assert!(Event::from_raw(Jev::Int, Some(b"+0x1F")).ok() == Some(Event::Int(31)));
+    assert!(Event::from_raw(Jev::Int, Some(b"-0X8000000000000000")).ok() == Some(Event::Int(i64::MIN)));
+    assert!(Event::from_raw(Jev::Int, Some(b"0x10000000000000000")).ok() == Some(Event::BigInt("0x10000000000000000")));
+    assert!(Event::from_raw(Jev::Float, Some(b".5")).ok() == Some(Event::Float(0.5)));
+    assert!(Event::from_raw(Jev::Float, Some(b"-Infinity")).ok() == Some(Event::Float(f64::NEG_INFINITY)));
+    match Event::from_raw(Jev::Float, Some(b"NaN")).ok() {
+        Some(Event::Float(f)) => assert!(f.is_nan()),
+        _ => panic!("expected NaN"),
+    }
+    assert!(Event::from_raw(Jev::Key, Some("é".as_bytes())).ok() == Some(Event::Key("é")));
+    assert!(Event::from_raw(Jev::True, Some(b"")).ok() == Some(Event::Bool(true)));
+    assert!(Event::from_raw(Jev::ObjectEnd, None).ok() == Some(Event::ObjectEnd));
+    assert!(Event::from_raw(Jev::DocumentEnd, Some(b"12")).ok() == Some(Event::DocumentEnd(12)));
+    assert!(Event::from_raw(Jev::Error, Some(b"unexpected character")).ok() == Some(Event::Error("unexpected character")));
+    match Event::from_raw(Jev::String, Some(b"\xed\xa0\x80")).err().map(|e| e.kind) {
+        Some(ErrorKind::Utf8) => (),
+        _ => panic!("expected utf8 error"),
+    }
+}
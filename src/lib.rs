@@ -1,14 +1,207 @@
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+#[macro_use]
+extern crate serde_derive;
+
+use std::cmp;
+use std::error;
+use std::fmt;
 use std::mem;
 use std::io::Read;
 use std::io;
 
-#[allow(dead_code)]
+#[cfg(feature = "serde")]
+pub mod de;
+#[cfg(feature = "serde")]
+pub mod ser;
+mod event;
+mod path;
+mod reader;
+mod render;
+mod validate;
+mod value;
+mod writer;
+
+pub use event::Event;
+pub use path::{track_path, Path, Segment};
+pub use reader::{EventReader, RawEvent};
+pub use validate::{validate, Diagnostic, Severity};
+pub use value::{Number, Value};
+pub use writer::{reformat, JsonWriter, Style};
+
+/// parser configuration.
+///
+/// the limits use 0 to mean no limit.
+#[derive(Clone)]
 pub struct Config {
     buffer_initial_size: usize,
     max_nesting: usize,
     max_data: usize,
     allow_c_comments: bool,
-    allow_yaml_comments: bool
+    allow_yaml_comments: bool,
+    emit_comments: bool,
+    allow_json5: bool,
+    allow_trailing_commas: bool,
+    allow_nan_infinity: bool,
+    strict: bool,
+    multi_document: bool,
+    json_lines: bool,
+    concatenated: bool,
+    json_seq: bool,
+    recover_errors: bool,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            buffer_initial_size: 4096,
+            max_nesting: 0,
+            max_data: 0,
+            allow_c_comments: false,
+            allow_yaml_comments: false,
+            emit_comments: false,
+            allow_json5: false,
+            allow_trailing_commas: false,
+            allow_nan_infinity: false,
+            strict: false,
+            multi_document: false,
+            json_lines: false,
+            concatenated: false,
+            json_seq: false,
+            recover_errors: false,
+        }
+    }
+}
+
+impl Config {
+    /// size of the chunks read from the input
+    pub fn with_buffer_initial_size(mut self, size: usize) -> Config {
+        self.buffer_initial_size = size;
+        self
+    }
+
+    /// maximum number of nested objects and arrays
+    pub fn with_max_nesting(mut self, max: usize) -> Config {
+        self.max_nesting = max;
+        self
+    }
+
+    /// maximum size in bytes of a single value (string, key or number)
+    pub fn with_max_data(mut self, max: usize) -> Config {
+        self.max_data = max;
+        self
+    }
+
+    /// allow C style comments: /* ... */
+    pub fn allow_c_comments(mut self, allow: bool) -> Config {
+        self.allow_c_comments = allow;
+        self
+    }
+
+    /// allow YAML style comments: # ... until the end of the line
+    pub fn allow_yaml_comments(mut self, allow: bool) -> Config {
+        self.allow_yaml_comments = allow;
+        self
+    }
+
+    /// report allowed comments as `Jev::Comment` events, with the full
+    /// comment text including its delimiters
+    pub fn emit_comments(mut self, emit: bool) -> Config {
+        self.emit_comments = emit;
+        self
+    }
+
+    /// allow the JSON5 syntax: identifier keys, single quoted strings,
+    /// trailing commas, hexadecimal integers, leading and trailing decimal
+    /// points, plus signs, Infinity and NaN, and escaped newlines in strings
+    pub fn allow_json5(mut self, allow: bool) -> Config {
+        self.allow_json5 = allow;
+        self
+    }
+
+    /// allow a comma after the last element of arrays and objects: [1,2,]
+    /// and {"a":1,}, generating the same events as without the comma.
+    /// this is also part of `allow_json5`.
+    pub fn allow_trailing_commas(mut self, allow: bool) -> Config {
+        self.allow_trailing_commas = allow;
+        self
+    }
+
+    /// allow the NaN, Infinity and -Infinity constants, as generated by
+    /// Python for example. they are reported as `Jev::Float` with the
+    /// constant text as data. this is also part of `allow_json5`.
+    pub fn allow_nan_infinity(mut self, allow: bool) -> Config {
+        self.allow_nan_infinity = allow;
+        self
+    }
+
+    /// accept exactly the documents allowed by RFC 8259.
+    ///
+    /// all the extensions are rejected whatever their own setting: comments,
+    /// a leading byte order mark, and empty documents.
+    pub fn strict(mut self, strict: bool) -> Config {
+        self.strict = strict;
+        self
+    }
+
+    /// accept a stream of documents one after another, separated by
+    /// whitespace, as in NDJSON. a `Jev::DocumentEnd` event is generated
+    /// after each document, with its index starting at 0 as data.
+    pub fn multi_document(mut self, multi: bool) -> Config {
+        self.multi_document = multi;
+        self
+    }
+
+    /// accept a stream of JSON Lines documents: like `multi_document`, but
+    /// each document has to be on its own line, and a newline inside a
+    /// document is an error.
+    pub fn json_lines(mut self, json_lines: bool) -> Config {
+        self.json_lines = json_lines;
+        self
+    }
+
+    /// accept a stream of concatenated documents: like `multi_document`,
+    /// but without whitespace needed between them, as in {}{}[]. a number
+    /// still has to be followed by a delimiter.
+    pub fn concatenated(mut self, concatenated: bool) -> Config {
+        self.concatenated = concatenated;
+        self
+    }
+
+    /// accept a JSON text sequence (RFC 7464): each document is preceded by
+    /// a record separator byte (0x1E). a corrupted or truncated record is
+    /// skipped up to the next record separator, with a `Jev::Error` event
+    /// reporting the error instead of failing the whole stream. with
+    /// `recover_errors`, the parsing resumes inside the record when possible.
+    pub fn json_seq(mut self, json_seq: bool) -> Config {
+        self.json_seq = json_seq;
+        self
+    }
+
+    /// keep parsing after an error in the input. the error is reported as a
+    /// `Jev::Error` event with its message, and the input is skipped up to
+    /// the next comma or closing bracket at the same depth, where the
+    /// parsing resumes. an error outside of any array or object skips to
+    /// the next document in multi document mode, or to the next line in
+    /// JSON Lines mode.
+    ///
+    /// the error event stands in place of the value it broke, so a key may
+    /// be followed by an error instead of its value. a value complete
+    /// before the error is still reported, and a key right after it starts
+    /// the next member as if the comma was there. the containers left open at the
+    /// end of the input or of a skipped record get their end events after
+    /// the error, so that the events stay balanced.
+    pub fn recover_errors(mut self, recover: bool) -> Config {
+        self.recover_errors = recover;
+        self
+    }
+
+    /* check an extension is enabled and not overridden by the strict mode */
+    fn extension(&self, allow: bool) -> bool {
+        allow && !self.strict
+    }
 }
 
 type JResult<T> = Result<T, JError>;
@@ -28,38 +221,241 @@ pub enum Jev {
     False,
     True,
     Null,
+    Comment,
+    DocumentEnd,
+    Error,
 }
 
-#[allow(non_camel_case_types)]
-pub enum JError {
-    /* SUCCESS = 0 */
-    /* running out of memory */
-    NO_MEMORY,
-    /* character < 32, except space newline tab */
-    BAD_CHAR,
-    /* trying to pop more object/array than pushed on the stack */
-    POP_EMPTY,
-    /* trying to pop wrong type of mode. popping array in object mode, vice versa */
-    POP_UNEXPECTED_MODE,
-    /* reach nesting limit on stack */
-    NESTING_LIMIT,
-    /* reach data limit on buffer */
-    DATA_LIMIT,
-    /* comment are not allowed with current configuration */
-    COMMENT_NOT_ALLOWED,
-    /* unexpected char in the current parser context */
-    UNEXPECTED_CHAR,
-    /* unicode low surrogate missing after high surrogate */
-    UNICODE_MISSING_LOW_SURROGATE,
-    /* unicode low surrogate missing without previous high surrogate */
-    UNICODE_UNEXPECTED_LOW_SURROGATE,
-    /* found a comma not in structure (array/object) */
-    COMMA_OUT_OF_STRUCTURE,
-    /* callback returns error */
-    CALLBACK,
-    /* utf8 stream is invalid */
-    UTF8,
-    IOERR(io::Error),
+/// position in the input: absolute byte offset, and line and column
+/// starting at 1. the column is counted in characters.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// span of an event in the input, from its first byte to right after its last byte
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// kind of token the parser can accept next, see `Parser::expected`
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Token {
+    /// start of any value: object, array, string, number or constant
+    Value,
+    /// object key
+    Key,
+    Colon,
+    Comma,
+    /// closing bracket of the innermost array
+    ArrayEnd,
+    /// closing bracket of the innermost object
+    ObjectEnd,
+    /// start of a comment allowed by the configuration
+    Comment,
+    /// any character of a string, or a backslash starting an escape
+    StringChar,
+    /// quote closing the string
+    Quote,
+    /// character after a backslash in a string
+    Escape,
+    Digit,
+    HexDigit,
+    Dot,
+    /// e or E starting the exponent of a number
+    Exponent,
+    /// sign of an exponent
+    Sign,
+    /// character of a JSON5 identifier key
+    IdentChar,
+    /// a given character, inside a constant or a comment delimiter
+    Char(char),
+    /// end of the input, the document being complete
+    End,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let desc = match *self {
+            Token::Value      => "a value",
+            Token::Key        => "a key",
+            Token::Colon      => "`:`",
+            Token::Comma      => "`,`",
+            Token::ArrayEnd   => "`]`",
+            Token::ObjectEnd  => "`}`",
+            Token::Comment    => "a comment",
+            Token::StringChar => "a string character",
+            Token::Quote      => "a closing quote",
+            Token::Escape     => "an escape character",
+            Token::Digit      => "a digit",
+            Token::HexDigit   => "a hexadecimal digit",
+            Token::Dot        => "`.`",
+            Token::Exponent   => "an exponent",
+            Token::Sign       => "a sign",
+            Token::IdentChar  => "an identifier character",
+            Token::Char(c)    => return write!(f, "`{}`", c),
+            Token::End        => "the end of input",
+        };
+        f.write_str(desc)
+    }
+}
+
+/// category of a parsing error
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ErrorKind {
+    /// running out of memory
+    NoMemory,
+    /// character < 32, except space newline tab
+    BadChar,
+    /// trying to pop more object/array than pushed on the stack
+    PopEmpty,
+    /// trying to pop wrong type of mode. popping array in object mode, vice versa
+    PopUnexpectedMode,
+    /// reach nesting limit on stack
+    NestingLimit,
+    /// reach data limit on buffer
+    DataLimit,
+    /// comment are not allowed with current configuration
+    CommentNotAllowed,
+    /// unexpected char in the current parser context
+    UnexpectedChar,
+    /// unicode low surrogate missing after high surrogate
+    UnicodeMissingLowSurrogate,
+    /// unicode low surrogate missing without previous high surrogate
+    UnicodeUnexpectedLowSurrogate,
+    /// found a comma not in structure (array/object)
+    CommaOutOfStructure,
+    /// callback returns error
+    Callback,
+    /// utf8 stream is invalid
+    Utf8,
+    /// end of input reached in the middle of a document, with the innermost open container if any
+    PrematureEof(Option<StackMode>),
+    /// error reading or writing, the underlying error is the `source()`
+    Io,
+    /// event not allowed at this point of the document when writing
+    UnexpectedEvent,
+    /// missing or invalid data for the event when writing
+    InvalidValue,
+    /// documents of a stream not separated as required by the mode
+    DocumentSeparator,
+    /// key appearing twice in the same object, reported by the validator
+    DuplicateKey,
+    /// error with a custom message, for example from a serde data structure
+    Custom,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match *self {
+            ErrorKind::NoMemory                      => "out of memory",
+            ErrorKind::BadChar                       => "invalid control character",
+            ErrorKind::PopEmpty                      => "closing bracket without matching opening bracket",
+            ErrorKind::PopUnexpectedMode             => "closing bracket doesn't match the opening bracket",
+            ErrorKind::NestingLimit                  => "nesting limit reached",
+            ErrorKind::DataLimit                     => "data limit reached",
+            ErrorKind::CommentNotAllowed             => "comments are not allowed",
+            ErrorKind::UnexpectedChar                => "unexpected character",
+            ErrorKind::UnicodeMissingLowSurrogate    => "unicode high surrogate not followed by a low surrogate",
+            ErrorKind::UnicodeUnexpectedLowSurrogate => "unicode low surrogate without high surrogate",
+            ErrorKind::CommaOutOfStructure           => "comma outside of an array or object",
+            ErrorKind::Callback                      => "callback error",
+            ErrorKind::Utf8                          => "invalid utf-8",
+            ErrorKind::PrematureEof(None)            => "premature end of input",
+            ErrorKind::PrematureEof(Some(StackMode::Object)) => "premature end of input in an object",
+            ErrorKind::PrematureEof(Some(StackMode::Array))  => "premature end of input in an array",
+            ErrorKind::Io                            => "I/O error",
+            ErrorKind::UnexpectedEvent               => "unexpected event",
+            ErrorKind::InvalidValue                  => "invalid value",
+            ErrorKind::DocumentSeparator             => "missing separator between documents",
+            ErrorKind::DuplicateKey                  => "duplicate key in object",
+            ErrorKind::Custom                        => "error",
+        };
+        f.write_str(msg)
+    }
+}
+
+/// parsing error, with the position in the input where it happened
+#[derive(Debug)]
+pub struct JError {
+    kind: ErrorKind,
+    pos: Option<Position>,
+    io: Option<io::Error>,
+    msg: Option<String>,
+    expected: Vec<Token>,
+}
+
+impl JError {
+    pub fn new(kind: ErrorKind) -> JError {
+        JError { kind, pos: None, io: None, msg: None, expected: vec![] }
+    }
+
+    /// error with a custom message
+    pub fn custom<T: fmt::Display>(msg: T) -> JError {
+        JError { kind: ErrorKind::Custom, pos: None, io: None, msg: Some(msg.to_string()), expected: vec![] }
+    }
+
+    /* set the position of the error, if not already known */
+    fn at(mut self, pos: Position) -> JError {
+        if self.pos.is_none() {
+            self.pos = Some(pos);
+        }
+        self
+    }
+
+    /* set the tokens expected where a syntax error happened */
+    fn expecting(mut self, parser: &Parser) -> JError {
+        if is_syntax_error(self.kind) && self.expected.is_empty() {
+            self.expected = expected_tokens(parser);
+        }
+        self
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn position(&self) -> Option<Position> {
+        self.pos
+    }
+
+    /// tokens that were expected where a syntax error happened, empty for
+    /// the other errors
+    pub fn expected(&self) -> &[Token] {
+        &self.expected
+    }
+}
+
+impl fmt::Display for JError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.msg {
+            Some(ref msg) => f.write_str(msg)?,
+            None          => self.kind.fmt(f)?,
+        }
+        if let Some(ref e) = self.io {
+            write!(f, ": {}", e)?;
+        }
+        if let Some(pos) = self.pos {
+            write!(f, " at line {}, column {}", pos.line, pos.column)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for JError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        self.io.as_ref().map(|e| e as &(dyn error::Error + 'static))
+    }
+}
+
+impl From<io::Error> for JError {
+    fn from(e: io::Error) -> JError {
+        JError { kind: ErrorKind::Io, pos: None, io: Some(e), msg: None, expected: vec![] }
+    }
 }
 
 #[repr(u8)]
@@ -86,6 +482,10 @@ enum C {
     Other, // all other
     Star, // star in C style comment
     Hash, // # for YAML comment
+    Squote, // ' for JSON5 strings
+    i, x, y, I, N, // letters of JSON5 hexadecimal, Infinity and NaN
+    Ident, // other letters, _ and $ of JSON5 identifiers
+    Rs, // record separator of JSON text sequences
     Error = 0xfe,
 }
 
@@ -99,7 +499,7 @@ enum C {
 ///
 #[repr(u8)]
 #[derive(Copy, Clone, PartialEq, Eq)]
-#[allow(non_camel_case_types)]
+#[allow(non_camel_case_types, clippy::enum_variant_names)]
 enum S {
     GO, // start
     OK, // ok
@@ -110,6 +510,7 @@ enum S {
     _A, // array
     _S, // string
     E0, // escape
+    E1, // JSON5 line continuation after a CR, before an optional LF
     U1, U2, U3, U4, // unicode states
     M0, Z0, I0, // number states
     R1, R2, // real states (after-dot digits)
@@ -120,6 +521,10 @@ enum S {
     C1, C2, C3, // C-comment states
     Y1, // YAML-comment state
     D1, D2, // multi unicode states
+    _I, // JSON5 identifier key
+    H1, H2, // JSON5 hexadecimal states
+    J1, J2, J3, J4, J5, J6, J7, // Infinity constant states
+    K1, K2, // NaN constant states
     // the following are actions that need to be taken
     KS = 0x80, // key separator
     SP, // comma separator
@@ -140,17 +545,43 @@ enum S {
     ZX, // integer detected by zero
     IX, // integer detected by 1-9
     UC, // Unicode character read
+    QB, // JSON5 single quoted string begin
+    SQ, // single quote in string
+    IB, // JSON5 identifier key begin
+    IE, // identifier key end by a whitespace
+    IC, // identifier key end by the colon
+    IS, // identifier key end by the slash of a C-comment
+    TA, // array ending after a trailing comma
+    TO, // object ending after a trailing comma
+    HX, // JSON5 hexadecimal integer
+    LD, // JSON5 double detected by a leading dot
+    PX, // JSON5 number detected by plus
+    IN, // Infinity
+    NA, // NaN
     __ = 0xff
 }
 
+/* states and actions consuming a character that is part of a string, number or constant */
+fn is_token_state(st: S) -> bool {
+    !matches!(st, S::GO | S::OK | S::_O | S::_K | S::CO | S::_V | S::_A
+                | S::C1 | S::C2 | S::C3 | S::Y1
+                | S::KS | S::SP | S::AB | S::AE | S::OB | S::OE | S::CB | S::YB | S::CE
+                | S::IE | S::IC | S::IS | S::TA | S::TO)
+}
+
+/* states where the value read so far is complete */
+fn is_complete_state(st: S) -> bool {
+    matches!(st, S::OK | S::Z0 | S::I0 | S::R2 | S::X3 | S::H2)
+}
+
 fn is_state_above_array(st: S) -> bool {
     let st_num : u8 = unsafe { mem::transmute(st) };
     let a_num  : u8 = unsafe { mem::transmute(S::_A) };
     st_num > a_num
 }
 
-const NR_CLASSES : usize = 34;
-const NR_STATES : usize = 37;
+const NR_CLASSES : usize = 42;
+const NR_STATES : usize = 50;
 
 /* map from character < 128 to classes. from 128 to 256 all C_OTHER */
 const CHARACTER_CLASS : [C;128] = [
@@ -162,10 +593,10 @@ const CHARACTER_CLASS : [C;128] = [
     C::Error, C::Error, C::Error, C::Error,
     C::Error, C::Error, C::Error, C::Error,
     C::Error, C::Error, C::Error, C::Error,
-    C::Error, C::Error, C::Error, C::Error,
+    C::Error, C::Error, C::Rs,    C::Error,
     // 32 to 63
     C::Space, C::Other, C::Quote, C::Hash,
-    C::Other, C::Other, C::Other, C::Other,
+    C::Ident, C::Other, C::Other, C::Squote,
     C::Other, C::Other, C::Star,  C::Plus,
     C::Comma, C::Minus, C::Dot,   C::Slash,
     C::Zero,  C::Digit, C::Digit, C::Digit,
@@ -174,120 +605,148 @@ const CHARACTER_CLASS : [C;128] = [
     C::Other, C::Other, C::Other, C::Other,
     // 64 to 95
     C::Other, C::Abcdf, C::Abcdf, C::Abcdf,
-    C::Abcdf, C::E,     C::Abcdf, C::Other,
-    C::Other, C::Other, C::Other, C::Other,
-    C::Other, C::Other, C::Other, C::Other,
-    C::Other, C::Other, C::Other, C::Other,
-    C::Other, C::Other, C::Other, C::Other,
-    C::Other, C::Other, C::Other, C::Lsqrb,
-    C::Backs, C::Rsqrb, C::Other, C::Other,
+    C::Abcdf, C::E,     C::Abcdf, C::Ident,
+    C::Ident, C::I,     C::Ident, C::Ident,
+    C::Ident, C::Ident, C::N,     C::Ident,
+    C::Ident, C::Ident, C::Ident, C::Ident,
+    C::Ident, C::Ident, C::Ident, C::Ident,
+    C::x,     C::Ident, C::Ident, C::Lsqrb,
+    C::Backs, C::Rsqrb, C::Other, C::Ident,
     // 96 to 127
     C::Other, C::a,     C::b,     C::c,
-    C::d,     C::e,     C::f,     C::Other,
-    C::Other, C::Other, C::Other, C::Other,
-    C::l,     C::Other, C::n,     C::Other,
-    C::Other, C::Other, C::r,     C::s,
-    C::t,     C::u,     C::Other, C::Other,
-    C::Other, C::Other, C::Other, C::Lcurb,
+    C::d,     C::e,     C::f,     C::Ident,
+    C::Ident, C::i,     C::Ident, C::Ident,
+    C::l,     C::Ident, C::n,     C::Ident,
+    C::Ident, C::Ident, C::r,     C::s,
+    C::t,     C::u,     C::Ident, C::Ident,
+    C::x,     C::y,     C::Ident, C::Lcurb,
     C::Other, C::Rcurb, C::Other, C::Other
 ];
 
 
 macro_rules! st {
-    ($a0:ident,$a1:ident,$a2:ident,$a3:ident,$a4:ident,$a5:ident,$a6:ident,$a7:ident,$a8:ident,$a9:ident,$a10:ident,$a11:ident,$a12:ident,$a13:ident,$a14:ident,$a15:ident,$a16:ident,$a17:ident,$a18:ident,$a19:ident,$a20:ident,$a21:ident,$a22:ident,$a23:ident,$a24:ident,$a25:ident,$a26:ident,$a27:ident,$a28:ident,$a29:ident,$a30:ident,$a31:ident,$a32:ident,$a33:ident) =>
+    ($a0:ident,$a1:ident,$a2:ident,$a3:ident,$a4:ident,$a5:ident,$a6:ident,$a7:ident,$a8:ident,$a9:ident,$a10:ident,$a11:ident,$a12:ident,$a13:ident,$a14:ident,$a15:ident,$a16:ident,$a17:ident,$a18:ident,$a19:ident,$a20:ident,$a21:ident,$a22:ident,$a23:ident,$a24:ident,$a25:ident,$a26:ident,$a27:ident,$a28:ident,$a29:ident,$a30:ident,$a31:ident,$a32:ident,$a33:ident,$a34:ident,$a35:ident,$a36:ident,$a37:ident,$a38:ident,$a39:ident,$a40:ident,$a41:ident) =>
 
-( [ S::$a0,S::$a1,S::$a2,S::$a3,S::$a4,S::$a5,S::$a6,S::$a7,S::$a8,S::$a9,S::$a10,S::$a11,S::$a12,S::$a13,S::$a14,S::$a15,S::$a16,S::$a17,S::$a18,S::$a19,S::$a20,S::$a21,S::$a22,S::$a23,S::$a24,S::$a25,S::$a26,S::$a27,S::$a28,S::$a29,S::$a30,S::$a31,S::$a32,S::$a33 ] )
+( [ S::$a0,S::$a1,S::$a2,S::$a3,S::$a4,S::$a5,S::$a6,S::$a7,S::$a8,S::$a9,S::$a10,S::$a11,S::$a12,S::$a13,S::$a14,S::$a15,S::$a16,S::$a17,S::$a18,S::$a19,S::$a20,S::$a21,S::$a22,S::$a23,S::$a24,S::$a25,S::$a26,S::$a27,S::$a28,S::$a29,S::$a30,S::$a31,S::$a32,S::$a33,S::$a34,S::$a35,S::$a36,S::$a37,S::$a38,S::$a39,S::$a40,S::$a41 ] )
 }
 
 const STATE_TRANS : [[S;NR_CLASSES];NR_STATES] = [
-/*GO*/ st!(GO,GO,GO,OB,__,AB,__,__,__,__,__,CB,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,YB),
-/*OK*/ st!(OK,OK,OK,__,OE,__,AE,__,SP,__,__,CB,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,YB),
-/*_O*/ st!(_O,_O,_O,__,OE,__,__,__,__,_S,__,CB,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,YB),
-/*_K*/ st!(_K,_K,_K,__,__,__,__,__,__,_S,__,CB,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,YB),
-/*CO*/ st!(CO,CO,CO,__,__,__,__,KS,__,__,__,CB,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,YB),
-/*_V*/ st!(_V,_V,_V,OB,__,AB,__,__,__,_S,__,CB,__,MX,__,ZX,IX,__,__,__,__,__,F1,__,N1,__,__,T1,__,__,__,__,__,YB),
-/*_A*/ st!(_A,_A,_A,OB,__,AB,AE,__,__,_S,__,CB,__,MX,__,ZX,IX,__,__,__,__,__,F1,__,N1,__,__,T1,__,__,__,__,__,YB),
-/****************************************************************************************************************/
-/*_S*/ st!(_S,__,__,_S,_S,_S,_S,_S,_S,SE,E0,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S),
-/*E0*/ st!(__,__,__,__,__,__,__,__,__,_S,_S,_S,__,__,__,__,__,__,_S,__,__,__,_S,__,_S,_S,__,_S,U1,__,__,__,__,__),
-/*U1*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,U2,U2,U2,U2,U2,U2,U2,U2,__,__,__,__,__,__,U2,U2,__,__,__),
-/*U2*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,U3,U3,U3,U3,U3,U3,U3,U3,__,__,__,__,__,__,U3,U3,__,__,__),
-/*U3*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,U4,U4,U4,U4,U4,U4,U4,U4,__,__,__,__,__,__,U4,U4,__,__,__),
-/*U4*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,UC,UC,UC,UC,UC,UC,UC,UC,__,__,__,__,__,__,UC,UC,__,__,__),
-/****************************************************************************************************************/
-/*M0*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,Z0,I0,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__),
-/*Z0*/ st!(OK,OK,OK,__,OE,__,AE,__,SP,__,__,CB,__,__,DF,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,YB),
-/*I0*/ st!(OK,OK,OK,__,OE,__,AE,__,SP,__,__,CB,__,__,DF,I0,I0,__,__,__,__,DE,__,__,__,__,__,__,__,__,DE,__,__,YB),
-/*R1*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,R2,R2,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__),
-/*R2*/ st!(OK,OK,OK,__,OE,__,AE,__,SP,__,__,CB,__,__,__,R2,R2,__,__,__,__,X1,__,__,__,__,__,__,__,__,X1,__,__,YB),
-/*X1*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,X2,X2,__,X3,X3,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__),
-/*X2*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,X3,X3,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__),
-/*X3*/ st!(OK,OK,OK,__,OE,__,AE,__,SP,__,__,__,__,__,__,X3,X3,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__),
-/****************************************************************************************************************/
-/*T1*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,T2,__,__,__,__,__,__,__,__),
-/*T2*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,T3,__,__,__,__,__),
-/*T3*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,TR,__,__,__,__,__,__,__,__,__,__,__,__),
-/*F1*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,F2,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__),
-/*F2*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,F3,__,__,__,__,__,__,__,__,__,__),
-/*F3*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,F4,__,__,__,__,__,__,__),
-/*F4*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,FA,__,__,__,__,__,__,__,__,__,__,__,__),
-/*N1*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,N2,__,__,__,__,__),
-/*N2*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,N3,__,__,__,__,__,__,__,__,__,__),
-/*N3*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,NU,__,__,__,__,__,__,__,__,__,__),
-/****************************************************************************************************************/
-/*C1*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,C2,__),
-/*C2*/ st!(C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C3,C2),
-/*C3*/ st!(C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,CE,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C3,C2),
-/*Y1*/ st!(Y1,CE,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1),
-/*D1*/ st!(__,__,__,__,__,__,__,__,__,__,D2,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__),
-/*D2*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,U1,__,__,__,__,__),
+/*GO*/ st!(GO,GO,GO,OB,__,AB,__,__,__,_S,__,CB,PX,MX,LD,ZX,IX,__,__,__,__,__,F1,__,N1,__,__,T1,__,__,__,__,__,YB,QB,__,__,__,IN,NA,__,GO),
+/*OK*/ st!(OK,OK,OK,__,OE,__,AE,__,SP,__,__,CB,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,YB,__,__,__,__,__,__,__,__),
+/*_O*/ st!(_O,_O,_O,__,OE,__,__,__,__,_S,__,CB,__,__,__,__,__,IB,IB,IB,IB,IB,IB,IB,IB,IB,IB,IB,IB,IB,IB,__,__,YB,QB,IB,IB,IB,IB,IB,IB,__),
+/*_K*/ st!(_K,_K,_K,__,TO,__,__,__,__,_S,__,CB,__,__,__,__,__,IB,IB,IB,IB,IB,IB,IB,IB,IB,IB,IB,IB,IB,IB,__,__,YB,QB,IB,IB,IB,IB,IB,IB,__),
+/*CO*/ st!(CO,CO,CO,__,__,__,__,KS,__,__,__,CB,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,YB,__,__,__,__,__,__,__,__),
+/*_V*/ st!(_V,_V,_V,OB,__,AB,TA,__,__,_S,__,CB,PX,MX,LD,ZX,IX,__,__,__,__,__,F1,__,N1,__,__,T1,__,__,__,__,__,YB,QB,__,__,__,IN,NA,__,__),
+/*_A*/ st!(_A,_A,_A,OB,__,AB,AE,__,__,_S,__,CB,PX,MX,LD,ZX,IX,__,__,__,__,__,F1,__,N1,__,__,T1,__,__,__,__,__,YB,QB,__,__,__,IN,NA,__,__),
+/****************************************************************************************************************************************/
+/*_S*/ st!(_S,__,__,_S,_S,_S,_S,_S,_S,SE,E0,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,SQ,_S,_S,_S,_S,_S,_S,__),
+/*E0*/ st!(__,_S,E1,__,__,__,__,__,__,_S,_S,_S,__,__,__,__,__,__,_S,__,__,__,_S,__,_S,_S,__,_S,U1,__,__,__,__,__,_S,__,__,__,__,__,__,__),
+/*E1*/ st!(_S,_S,__,_S,_S,_S,_S,_S,_S,SE,E0,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,_S,SQ,_S,_S,_S,_S,_S,_S,__),
+/*U1*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,U2,U2,U2,U2,U2,U2,U2,U2,__,__,__,__,__,__,U2,U2,__,__,__,__,__,__,__,__,__,__,__),
+/*U2*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,U3,U3,U3,U3,U3,U3,U3,U3,__,__,__,__,__,__,U3,U3,__,__,__,__,__,__,__,__,__,__,__),
+/*U3*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,U4,U4,U4,U4,U4,U4,U4,U4,__,__,__,__,__,__,U4,U4,__,__,__,__,__,__,__,__,__,__,__),
+/*U4*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,UC,UC,UC,UC,UC,UC,UC,UC,__,__,__,__,__,__,UC,UC,__,__,__,__,__,__,__,__,__,__,__),
+/****************************************************************************************************************************************/
+/*M0*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,LD,Z0,I0,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,IN,NA,__,__),
+/*Z0*/ st!(OK,OK,OK,__,OE,__,AE,__,SP,__,__,CB,__,__,DF,__,__,__,__,__,__,DE,__,__,__,__,__,__,__,__,DE,__,__,YB,__,__,HX,__,__,__,__,__),
+/*I0*/ st!(OK,OK,OK,__,OE,__,AE,__,SP,__,__,CB,__,__,DF,I0,I0,__,__,__,__,DE,__,__,__,__,__,__,__,__,DE,__,__,YB,__,__,__,__,__,__,__,__),
+/*R1*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,R2,R2,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__),
+/*R2*/ st!(OK,OK,OK,__,OE,__,AE,__,SP,__,__,CB,__,__,__,R2,R2,__,__,__,__,X1,__,__,__,__,__,__,__,__,X1,__,__,YB,__,__,__,__,__,__,__,__),
+/*X1*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,X2,X2,__,X3,X3,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__),
+/*X2*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,X3,X3,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__),
+/*X3*/ st!(OK,OK,OK,__,OE,__,AE,__,SP,__,__,__,__,__,__,X3,X3,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__),
+/****************************************************************************************************************************************/
+/*T1*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,T2,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__),
+/*T2*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,T3,__,__,__,__,__,__,__,__,__,__,__,__,__),
+/*T3*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,TR,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__),
+/*F1*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,F2,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__),
+/*F2*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,F3,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__),
+/*F3*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,F4,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__),
+/*F4*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,FA,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__),
+/*N1*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,N2,__,__,__,__,__,__,__,__,__,__,__,__,__),
+/*N2*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,N3,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__),
+/*N3*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,NU,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__),
+/****************************************************************************************************************************************/
+/*C1*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,C2,__,__,__,__,__,__,__,__,__),
+/*C2*/ st!(C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C3,C2,C2,C2,C2,C2,C2,C2,C2,__),
+/*C3*/ st!(C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,CE,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C2,C3,C2,C2,C2,C2,C2,C2,C2,C2,__),
+/*Y1*/ st!(Y1,CE,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,Y1,__),
+/*D1*/ st!(__,__,__,__,__,__,__,__,__,__,D2,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__),
+/*D2*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,U1,__,__,__,__,__,__,__,__,__,__,__,__,__),
+/****************************************************************************************************************************************/
+/*_I*/ st!(IE,IE,IE,__,__,__,__,IC,__,__,__,IS,__,__,__,_I,_I,_I,_I,_I,_I,_I,_I,_I,_I,_I,_I,_I,_I,_I,_I,__,__,__,__,_I,_I,_I,_I,_I,_I,__),
+/*H1*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,H2,H2,H2,H2,H2,H2,H2,H2,__,__,__,__,__,__,H2,H2,__,__,__,__,__,__,__,__,__,__,__),
+/*H2*/ st!(OK,OK,OK,__,OE,__,AE,__,SP,__,__,CB,__,__,__,H2,H2,H2,H2,H2,H2,H2,H2,__,__,__,__,__,__,H2,H2,__,__,YB,__,__,__,__,__,__,__,__),
+/*J1*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,J2,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__),
+/*J2*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,J3,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__),
+/*J3*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,J4,__,__,__,__,__,__),
+/*J4*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,J5,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__),
+/*J5*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,J6,__,__,__,__,__,__),
+/*J6*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,J7,__,__,__,__,__,__,__,__,__,__,__,__,__,__),
+/*J7*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,OK,__,__,__,__),
+/*K1*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,K2,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__),
+/*K2*/ st!(__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,__,OK,__,__),
 ];
 
-/* map from (previous state+new character class) to the buffer policy. ignore=0/append=1/escape=2 */
+/* map from (previous state+new character class) to the buffer policy. ignore=0/append=1/escape=2/comment=3 */
 const BUFFER_POLICY_TABLE : [[u8;NR_CLASSES];NR_STATES] = [
-/*          white                                                                            ABCDF  other     */
-/*      sp nl  |  {  }  [  ]  :  ,  "  \  /  +  -  .  0  19 a  b  c  d  e  f  l  n  r  s  t  u  |  E  |  *  # */
-/*GO*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
-/*OK*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
-/*_O*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
-/*_K*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
-/*CO*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
-/*_V*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
-/*_A*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
-/**************************************************************************************************************/
-/*_S*/ [ 1, 0, 0, 1, 1, 1, 1, 1, 1, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1 ],
-/*E0*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 2, 2, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 2, 0, 2, 2, 0, 2, 0, 0, 0, 0, 0, 0 ],
-/*U1*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0 ],
-/*U2*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0 ],
-/*U3*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0 ],
-/*U4*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0 ],
-/**************************************************************************************************************/
-/*M0*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
-/*Z0*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
-/*I0*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0 ],
-/*R1*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
-/*R2*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0 ],
-/*X1*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
-/*X2*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
-/*X3*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
-/**************************************************************************************************************/
-/*T1*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
-/*T2*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
-/*T3*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
-/*F1*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
-/*F2*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
-/*F3*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
-/*F4*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
-/*N1*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
-/*N2*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
-/*N3*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
-/**************************************************************************************************************/
-/*C1*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
-/*C2*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
-/*C3*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
-/*Y1*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
-/*D1*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
-/*D2*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*          white                                                                            ABCDF  other                     id     */
+/*      sp nl  |  {  }  [  ]  :  ,  "  \  /  +  -  .  0  19 a  b  c  d  e  f  l  n  r  s  t  u  |  E  |  *  #  '  i  x  y  I  N  |  rs */
+/*GO*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0 ],
+/*OK*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*_O*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 0 ],
+/*_K*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 0 ],
+/*CO*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*_V*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0 ],
+/*_A*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0 ],
+/**************************************************************************************************************************************/
+/*_S*/ [ 1, 0, 0, 1, 1, 1, 1, 1, 1, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 1, 1, 1, 1, 1, 1, 0 ],
+/*E0*/ [ 0, 2, 2, 0, 0, 0, 0, 0, 0, 2, 2, 2, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 2, 0, 2, 2, 0, 2, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0 ],
+/*E1*/ [ 1, 0, 0, 1, 1, 1, 1, 1, 1, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 1, 1, 1, 1, 1, 1, 0 ],
+/*U1*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*U2*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*U3*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*U4*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/**************************************************************************************************************************************/
+/*M0*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0 ],
+/*Z0*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0 ],
+/*I0*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*R1*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*R2*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*X1*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*X2*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*X3*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/**************************************************************************************************************************************/
+/*T1*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*T2*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*T3*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*F1*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*F2*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*F3*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*F4*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*N1*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*N2*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*N3*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/**************************************************************************************************************************************/
+/*C1*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*C2*/ [ 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 0 ],
+/*C3*/ [ 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 0 ],
+/*Y1*/ [ 3, 0, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 0 ],
+/*D1*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*D2*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/**************************************************************************************************************************************/
+/*_I*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 0 ],
+/*H1*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*H2*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*J1*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*J2*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*J3*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0 ],
+/*J4*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*J5*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0 ],
+/*J6*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*J7*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0 ],
+/*K1*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0 ],
+/*K2*/ [ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0 ],
     ];
 
 const __ : u8 = 0xff;
@@ -346,13 +805,13 @@ fn is_high_surrogate(uc: u32) -> bool { (uc & 0xfc00) == 0xd800 }
 fn is_low_surrogate(uc: u32) -> bool { (uc & 0xfc00) == 0xdc00 }
 
 #[repr(u8)]
-#[derive(PartialEq)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum StackMode {
     Object,
     Array
 }
 
-pub type Callback = Fn(Jev, Option<&Vec<u8> >) -> Result<(), JError>;
+pub type Callback<'a> = dyn Fn(Jev, Option<&Vec<u8> >, Span) -> Result<(), JError> + 'a;
 
 pub struct Parser {
     config: Config,
@@ -365,13 +824,31 @@ pub struct Parser {
     //stack_size: usize,
     jtype: Option<Jev>,
     buffer: Vec<u8>,
-    buffer_size: usize
+    readbuf: Vec<u8>,
+    pos: Position,
+    token: Span,
+    comment_start: Position,
+    bom: usize,
+    quote: u8,
+    documents: usize,
+    separated: bool,
+    skip: Option<Skip>,
+    errors: Option<Vec<(ErrorKind, Span)>>,
+}
+
+/* input skipped after an error, until the parsing can resume */
+struct Skip {
+    depth: usize,
+    quote: Option<u8>,
+    escape: bool,
 }
 
+const START_POSITION : Position = Position { offset: 0, line: 1, column: 1 };
+
 // initialize a parser structure taking a config,
 pub fn init(config: Config) -> Parser {
     Parser { 
-        config: config,
+        config,
         state: S::GO,
         save_state: S::GO,
         expecting_key: false,
@@ -380,69 +857,91 @@ pub fn init(config: Config) -> Parser {
         stack: vec![],
         jtype: None,
         buffer: vec![],
-        buffer_size: 2048,
+        readbuf: vec![],
+        pos: START_POSITION,
+        token: Span { start: START_POSITION, end: START_POSITION },
+        comment_start: START_POSITION,
+        bom: 0,
+        quote: b'"',
+        documents: 0,
+        separated: false,
+        skip: None,
+        errors: None,
     }
 }
 
-fn state_push(parser: &mut Parser, mode: StackMode) {
-    parser.stack.push(mode);
+fn state_push(parser: &mut Parser, mode: StackMode) -> JResult0 {
+    if parser.config.max_nesting > 0 && parser.stack.len() >= parser.config.max_nesting {
+        Err(JError::new(ErrorKind::NestingLimit))
+    } else {
+        parser.stack.push(mode);
+        Ok(())
+    }
 }
 
 fn state_pop(parser: &mut Parser, mode: StackMode) -> JResult0 {
-    match parser.stack.pop() {
-        None    => Err(JError::POP_EMPTY),
-        Some(m) =>
-            if m == mode { Ok (()) } else { Err(JError::POP_UNEXPECTED_MODE) }
+    match parser.stack.last() {
+        None    => Err(JError::new(ErrorKind::PopEmpty)),
+        Some(&m) if m == mode => {
+            parser.stack.pop();
+            Ok(())
+        },
+        // the mismatched container stays open, for the recovery to close it later
+        Some(_) => Err(JError::new(ErrorKind::PopUnexpectedMode)),
     }
 }
 
 fn buffer_push(parser: &mut Parser, c: u8) -> JResult0 {
-    if parser.buffer.len() >= parser.buffer_size {
-        Err(JError::DATA_LIMIT)
+    if parser.config.max_data > 0 && parser.buffer.len() >= parser.config.max_data {
+        Err(JError::new(ErrorKind::DataLimit))
     } else {
         parser.buffer.push(c);
         Ok(())
     }
 }
 
+/* push the character denoted by the escape sequence \\<next> */
 fn buffer_push_escape(parser: &mut Parser, next: u8) -> JResult0 {
     let c =
         match next {
-            0x62 /* 'b' */  => 0x7,
-            0x66 /* 'f' */  => 0xc,
-            0x6e /* 'n' */  => 0x10,
-            0x72 /* 'r' */  => 0x13,
-            0x74 /* 't' */  => 0x9,
-            0x22 /* '"' */  => 0x22,
-            0x2f /* '/' */  => 0x2f,
-            0x5c /* '\\' */ => 0x5c,
-            _               => 0
+            b'b'  => 0x08,
+            b'f'  => 0x0c,
+            b'n'  => 0x0a,
+            b'r'  => 0x0d,
+            b't'  => 0x09,
+            b'"'  => b'"',
+            b'/'  => b'/',
+            b'\\' => b'\\',
+            b'\'' if json5(parser) => b'\'',
+            b'\n' | b'\r' if json5(parser) => return Ok(()),
+            _     => return Err(JError::new(ErrorKind::UnexpectedChar)),
         };
     buffer_push(parser, c)
 }
 
 fn do_callback_withbuf(parser: &mut Parser, cb: &Callback, ty: Jev) -> JResult0 {
-    cb(ty, Some(&parser.buffer))
+    cb(ty, Some(&parser.buffer), parser.token)
 }
 
-fn do_callback(_: &mut Parser, cb: &Callback, ty: Jev) -> JResult0 {
-    cb(ty, None)
+/* container events are always on the current character, which is a single byte */
+fn do_callback(parser: &mut Parser, cb: &Callback, ty: Jev) -> JResult0 {
+    let start = parser.pos;
+    let end = Position { offset: start.offset + 1, line: start.line, column: start.column + 1 };
+    cb(ty, None, Span { start, end })
 }
 
 fn do_buffer(parser: &mut Parser, cb: &Callback) -> JResult0 {
-    match parser.jtype {
-        Some(jty) =>
-            match jty {
-                Jev::Key    => try!(do_callback_withbuf(parser, cb, jty)),
-                Jev::String => try!(do_callback_withbuf(parser, cb, jty)),
-                Jev::Float  => try!(do_callback_withbuf(parser, cb, jty)),
-                Jev::Int    => try!(do_callback_withbuf(parser, cb, jty)),
-                Jev::Null   => try!(do_callback_withbuf(parser, cb, jty)),
-                Jev::True   => try!(do_callback_withbuf(parser, cb, jty)),
-                Jev::False  => try!(do_callback_withbuf(parser, cb, jty)),
-                _           => ()
-            },
-        _         => (),
+    if let Some(jty) = parser.jtype.take() {
+        match jty {
+            Jev::Key    => do_callback_withbuf(parser, cb, jty)?,
+            Jev::String => do_callback_withbuf(parser, cb, jty)?,
+            Jev::Float  => do_callback_withbuf(parser, cb, jty)?,
+            Jev::Int    => do_callback_withbuf(parser, cb, jty)?,
+            Jev::Null   => do_callback_withbuf(parser, cb, jty)?,
+            Jev::True   => do_callback_withbuf(parser, cb, jty)?,
+            Jev::False  => do_callback_withbuf(parser, cb, jty)?,
+            _           => ()
+        }
     };
     parser.buffer.clear();
     Ok(())
@@ -460,9 +959,9 @@ fn update_simple(parser: &mut Parser, ty: Option<Jev>, nst: S) -> JResult0 {
 fn update_callbk<F>(parser: &mut Parser, cb: &Callback, ty: Option<Jev>, nst: S, dobuf: bool, per_ty_cb: F) -> JResult0
     where F : Fn(&mut Parser) -> JResult0 {
     if dobuf {
-        try!(do_buffer(parser, cb));
+        do_buffer(parser, cb)?;
     }
-    try!(per_ty_cb(parser));
+    per_ty_cb(parser)?;
     match nst {
         S::__ => (),
         _     => parser.state = nst
@@ -480,113 +979,215 @@ fn decode_unicode_char(parser: &mut Parser) -> JResult0 {
              | HEXTABLE[parser.buffer[offset - 1] as usize];
 
     parser.buffer.truncate(offset - 4);
-    
-    if parser.unicode_multi > 0 && uval < 0x80 {
-        parser.buffer.push(uval as u8);
-        return Ok(())
-    }
 
     if parser.unicode_multi > 0 {
         if !is_low_surrogate(uval) {
-            return Err(JError::UNICODE_MISSING_LOW_SURROGATE);
+            return Err(JError::new(ErrorKind::UnicodeMissingLowSurrogate));
         }
-
         let uval = 0x10000 + ((parser.unicode_multi & 0x3ff) << 10) + (uval & 0x3ff);
-        parser.buffer.push(((uval >> 18) | 0xf0) as u8);
-        parser.buffer.push((((uval >> 12) & 0x3f) | 0x80) as u8);
-        parser.buffer.push((((uval >> 6) & 0x3f) | 0x80) as u8);
-        parser.buffer.push(((uval & 0x3f) | 0x80) as u8);
         parser.unicode_multi = 0;
-        return Ok(());
+        return buffer_push_utf8(parser, uval);
     }
 
     if is_low_surrogate(uval) {
-        return Err(JError::UNICODE_UNEXPECTED_LOW_SURROGATE);
+        return Err(JError::new(ErrorKind::UnicodeUnexpectedLowSurrogate));
     }
     if is_high_surrogate(uval) {
         parser.unicode_multi = uval;
         return Ok(());
     }
+    buffer_push_utf8(parser, uval)
+}
 
-    if uval < 0x800 {
-        parser.buffer.push(((uval >> 6) | 0xc0) as u8);
-        parser.buffer.push(((uval & 0x3f) | 0x80) as u8);
+/* push the UTF-8 encoding of a unicode scalar value */
+fn buffer_push_utf8(parser: &mut Parser, uval: u32) -> JResult0 {
+    if uval < 0x80 {
+        buffer_push(parser, uval as u8)
+    } else if uval < 0x800 {
+        buffer_push(parser, ((uval >> 6) | 0xc0) as u8)?;
+        buffer_push(parser, ((uval & 0x3f) | 0x80) as u8)
+    } else if uval < 0x10000 {
+        buffer_push(parser, ((uval >> 12) | 0xe0) as u8)?;
+        buffer_push(parser, (((uval >> 6) & 0x3f) | 0x80) as u8)?;
+        buffer_push(parser, ((uval & 0x3f) | 0x80) as u8)
     } else {
-        parser.buffer.push(((uval >> 12) | 0xe0) as u8);
-        parser.buffer.push((((uval >> 6) & 0x3f) | 0x80) as u8);
-        parser.buffer.push((((uval >> 0) & 0x3f) | 0x80) as u8);
+        buffer_push(parser, ((uval >> 18) | 0xf0) as u8)?;
+        buffer_push(parser, (((uval >> 12) & 0x3f) | 0x80) as u8)?;
+        buffer_push(parser, (((uval >> 6) & 0x3f) | 0x80) as u8)?;
+        buffer_push(parser, ((uval & 0x3f) | 0x80) as u8)
     }
-    Ok(())
 }
 
 
 // ********************************************************************** 
 fn act_uc(parser: &mut Parser) -> JResult0 {
-    try!(decode_unicode_char(parser));
+    decode_unicode_char(parser)?;
     parser.state = if parser.unicode_multi > 0 { S::D1 } else { S::_S };
     Ok(())
 }
 
 fn act_yb(parser: &mut Parser) -> JResult0 {
-    if !parser.config.allow_yaml_comments {
-        Err(JError::COMMENT_NOT_ALLOWED)
+    if !parser.config.extension(parser.config.allow_yaml_comments) {
+        Err(JError::new(ErrorKind::CommentNotAllowed))
     } else {
-        parser.save_state = parser.state;
-        Ok(())
+        comment_begin(parser, b'#')
     }
 }
 
 fn act_cb(parser: &mut Parser) -> JResult0 {
-    if !parser.config.allow_c_comments {
-        Err(JError::COMMENT_NOT_ALLOWED)
+    if !parser.config.extension(parser.config.allow_c_comments) {
+        Err(JError::new(ErrorKind::CommentNotAllowed))
     } else {
-        parser.save_state = parser.state;
-        Ok(())
+        comment_begin(parser, b'/')
+    }
+}
+
+fn comment_begin(parser: &mut Parser, ch: u8) -> JResult0 {
+    parser.save_state = parser.state;
+    parser.comment_start = parser.pos;
+    if parser.config.emit_comments {
+        buffer_push(parser, ch)?;
+    }
+    Ok(())
+}
+
+/* report the comment in the buffer, ending at the given position */
+fn do_comment(parser: &mut Parser, cb: &Callback, end: Position) -> JResult0 {
+    if parser.config.emit_comments {
+        cb(Jev::Comment, Some(&parser.buffer), Span { start: parser.comment_start, end })?;
+        parser.buffer.clear();
     }
+    Ok(())
 }
 
-fn act_ce(parser: &mut Parser) -> JResult0 {
+fn act_ce(parser: &mut Parser, cb: &Callback) -> JResult0 {
+    // a C comment ends on the '/' included in the comment, a YAML comment on the newline
+    let mut end = parser.pos;
+    if parser.state == S::C3 {
+        end.offset += 1;
+        end.column += 1;
+    }
+    do_comment(parser, cb, end)?;
     parser.state = if is_state_above_array(parser.save_state) { S::OK } else { parser.save_state };
     Ok(())
 }
 
+/* the container is pushed first, so that a start event always gets its end */
 fn act_ob(parser: &mut Parser, cb: &Callback) -> JResult0 {
-    try!(do_callback(parser, cb, Jev::ObjectStart));
-    state_push(parser, StackMode::Object);
+    state_push(parser, StackMode::Object)?;
+    do_callback(parser, cb, Jev::ObjectStart)?;
     parser.expecting_key = true;
     Ok(())
 }
 
 fn act_oe(parser: &mut Parser, cb: &Callback) -> JResult0 {
-    try!(state_pop(parser, StackMode::Object));
-    try!(do_callback(parser, cb, Jev::ObjectEnd));
+    state_pop(parser, StackMode::Object)?;
+    do_callback(parser, cb, Jev::ObjectEnd)?;
     parser.expecting_key = false;
     Ok(())
 }
 
 fn act_ab(parser: &mut Parser, cb: &Callback) -> JResult0 {
-    try!(do_callback(parser, cb, Jev::ArrayStart));
-    state_push(parser, StackMode::Array);
-    Ok(())
+    state_push(parser, StackMode::Array)?;
+    do_callback(parser, cb, Jev::ArrayStart)
 }
 
 fn act_ae(parser: &mut Parser, cb: &Callback) -> JResult0 {
-    try!(state_pop(parser, StackMode::Array));
+    state_pop(parser, StackMode::Array)?;
     do_callback(parser, cb, Jev::ArrayEnd)
 }
 
 fn act_se(parser: &mut Parser, cb : &Callback) -> JResult0 {
+    if parser.quote == b'"' { string_end(parser, cb) } else { buffer_push(parser, b'"') }
+}
+
+fn string_end(parser: &mut Parser, cb : &Callback) -> JResult0 {
     let ty = if parser.expecting_key { Jev::Key } else { Jev::String };
-    try!(do_callback_withbuf(parser, cb, ty));
+    do_callback_withbuf(parser, cb, ty)?;
     parser.buffer.clear();
     parser.state = if parser.expecting_key { S::CO } else { S::OK };
     parser.expecting_key = false;
+    parser.quote = b'"';
+    Ok(())
+}
+
+fn json5(parser: &Parser) -> bool {
+    parser.config.extension(parser.config.allow_json5)
+}
+
+fn trailing_commas(parser: &Parser) -> bool {
+    parser.config.extension(parser.config.allow_json5 || parser.config.allow_trailing_commas)
+}
+
+fn nan_constants(parser: &Parser) -> bool {
+    parser.config.extension(parser.config.allow_json5 || parser.config.allow_nan_infinity)
+}
+
+fn act_nan_infinity(parser: &mut Parser) -> JResult0 {
+    if nan_constants(parser) {
+        Ok(())
+    } else {
+        Err(JError::new(ErrorKind::UnexpectedChar))
+    }
+}
+
+/* a signed NaN is only JSON5, the other constants come without a sign */
+fn act_nan(parser: &mut Parser) -> JResult0 {
+    if parser.state == S::M0 { act_json5(parser) } else { act_nan_infinity(parser) }
+}
+
+fn act_json5(parser: &mut Parser) -> JResult0 {
+    if json5(parser) { Ok(()) } else { Err(JError::new(ErrorKind::UnexpectedChar)) }
+}
+
+fn act_qb(parser: &mut Parser) -> JResult0 {
+    act_json5(parser)?;
+    parser.quote = b'\'';
+    Ok(())
+}
+
+/* a quote only ends the string opened by the same quote */
+fn act_sq(parser: &mut Parser, cb: &Callback) -> JResult0 {
+    if parser.quote == b'\'' { string_end(parser, cb) } else { buffer_push(parser, b'\'') }
+}
+
+fn act_ie(parser: &mut Parser, cb: &Callback) -> JResult0 {
+    do_callback_withbuf(parser, cb, Jev::Key)?;
+    parser.buffer.clear();
+    parser.expecting_key = false;
+    Ok(())
+}
+
+/* the comment is after the key, so the colon is expected after it */
+fn act_is(parser: &mut Parser, cb: &Callback) -> JResult0 {
+    act_ie(parser, cb)?;
+    parser.state = S::CO;
+    act_cb(parser)
+}
+
+/* after a comma, a value is expected in an array, so ] is only valid there */
+fn act_ta(parser: &mut Parser, cb: &Callback) -> JResult0 {
+    if !trailing_commas(parser) || parser.stack.last() != Some(&StackMode::Array) {
+        return Err(JError::new(ErrorKind::UnexpectedChar));
+    }
+    act_ae(parser, cb)
+}
+
+fn act_to(parser: &mut Parser, cb: &Callback) -> JResult0 {
+    if !trailing_commas(parser) {
+        return Err(JError::new(ErrorKind::UnexpectedChar));
+    }
+    act_oe(parser, cb)
+}
+
+fn act_df(parser: &mut Parser) -> JResult0 {
+    parser.state = if json5(parser) { S::R2 } else { S::R1 };
     Ok(())
 }
 
 fn act_sp(parser: &mut Parser) -> JResult0 {
-    if parser.stack.len() == 0 {
-        Err(JError::COMMA_OUT_OF_STRUCTURE)
+    if parser.stack.is_empty() {
+        Err(JError::new(ErrorKind::CommaOutOfStructure))
     } else {
         parser.state =
             if parser.stack[parser.stack.len() - 1] == StackMode::Object {
@@ -602,24 +1203,37 @@ fn act_sp(parser: &mut Parser) -> JResult0 {
 fn do_action(parser: &mut Parser, cb: &Callback, next_state: S) -> JResult0 {
     match next_state {
         S::KS => update_simple(parser, None, S::_V),
-        S::SP => update_callbk(parser, cb, None, S::__, false, |p| act_sp(p) ),
+        S::SP => update_callbk(parser, cb, None, S::__, true, act_sp),
         S::AB => update_callbk(parser, cb, None, S::_A, false, |p| act_ab(p, cb) ),
         S::AE => update_callbk(parser, cb, None, S::OK, true, |p| act_ae(p, cb) ),
         S::OB => update_callbk(parser, cb, None, S::_O, false, |p| act_ob(p, cb) ),
         S::OE => update_callbk(parser, cb, None, S::OK, true, |p| act_oe(p, cb) ),
-        S::CB => update_callbk(parser, cb, None, S::C1, true, |p| act_cb(p) ),
-        S::YB => update_callbk(parser, cb, None, S::Y1, true, |p| act_yb(p) ),
-        S::CE => update_callbk(parser, cb, None, S::__, false, |p| act_ce(p) ),
+        S::CB => update_callbk(parser, cb, None, S::C1, true, act_cb),
+        S::YB => update_callbk(parser, cb, None, S::Y1, true, act_yb),
+        S::CE => update_callbk(parser, cb, None, S::__, false, |p| act_ce(p, cb) ),
         S::FA => update_simple(parser, Some(Jev::False), S::OK),
         S::TR => update_simple(parser, Some(Jev::True),  S::OK),
         S::NU => update_simple(parser, Some(Jev::Null),  S::OK),
         S::DE => update_simple(parser, Some(Jev::Float), S::X1),
-        S::DF => update_simple(parser, Some(Jev::Float), S::R1),
+        S::DF => update_callbk(parser, cb, Some(Jev::Float), S::__, false, act_df),
         S::SE => update_callbk(parser, cb, None, S::__, false, |p| act_se(p, cb) ),
         S::MX => update_simple(parser, Some(Jev::Int), S::M0),
         S::ZX => update_simple(parser, Some(Jev::Int), S::Z0),
         S::IX => update_simple(parser, Some(Jev::Int), S::I0),
-        S::UC => update_callbk(parser, cb, None, S::__, false, |p| act_uc(p) ),
+        S::UC => update_callbk(parser, cb, None, S::__, false, act_uc),
+        S::QB => update_callbk(parser, cb, None, S::_S, false, act_qb),
+        S::SQ => update_callbk(parser, cb, None, S::__, false, |p| act_sq(p, cb) ),
+        S::IB => update_callbk(parser, cb, None, S::_I, false, act_json5),
+        S::IE => update_callbk(parser, cb, None, S::CO, false, |p| act_ie(p, cb) ),
+        S::IC => update_callbk(parser, cb, None, S::_V, false, |p| act_ie(p, cb) ),
+        S::IS => update_callbk(parser, cb, None, S::C1, false, |p| act_is(p, cb) ),
+        S::TA => update_callbk(parser, cb, None, S::OK, false, |p| act_ta(p, cb) ),
+        S::TO => update_callbk(parser, cb, None, S::OK, false, |p| act_to(p, cb) ),
+        S::HX => update_callbk(parser, cb, Some(Jev::Int), S::H1, false, act_json5),
+        S::LD => update_callbk(parser, cb, Some(Jev::Float), S::R1, false, act_json5),
+        S::PX => update_callbk(parser, cb, Some(Jev::Int), S::M0, false, act_json5),
+        S::IN => update_callbk(parser, cb, Some(Jev::Float), S::J1, false, act_nan_infinity),
+        S::NA => update_callbk(parser, cb, Some(Jev::Float), S::K1, false, act_nan),
         _     => Ok(())
     }
 }
@@ -627,19 +1241,19 @@ fn do_action(parser: &mut Parser, cb: &Callback, next_state: S) -> JResult0 {
 fn get_next_class(parser : &mut Parser, ch : u8) -> JResult<C> {
     if parser.utf8_multibyte_left > 0 {
         if UTF8_CONTINUATION_TABLE[ch as usize] != 0 {
-            Err(JError::UTF8)
+            Err(JError::new(ErrorKind::Utf8))
         } else {
-            parser.utf8_multibyte_left = parser.utf8_multibyte_left - 1;
+            parser.utf8_multibyte_left -= 1;
             Ok(C::Other)
         }
     } else {
         let multibytes = UTF8_HEADER_TABLE[ch as usize];
         if multibytes == 0xff {
-            Err(JError::UTF8)
+            Err(JError::new(ErrorKind::Utf8))
         } else {
             let next_class = if multibytes > 0 { C::Other } else { CHARACTER_CLASS[ch as usize] };
-            if next_class == C::Error {
-                Err(JError::BAD_CHAR)
+            if next_class == C::Error || (next_class == C::Rs && !parser.config.json_seq) {
+                Err(JError::new(ErrorKind::BadChar))
             } else {
                 parser.utf8_multibyte_left = multibytes;
                 Ok(next_class)
@@ -648,49 +1262,1190 @@ fn get_next_class(parser : &mut Parser, ch : u8) -> JResult<C> {
     }
 }
 
-pub fn parse_data(mut parser: Parser, cb: &Callback, inp: &mut [u8]) -> Result<(),JError> {
-    for i in 0..inp.len() {
-        let ch = inp[i];
+/// parse a chunk of data, keeping all the parsing state in the parser.
+///
+/// a document can be split at any byte boundary and fed chunk by chunk;
+/// the events generated are the same as if the whole document had been
+/// given in one go.
+pub fn parse_data(parser: &mut Parser, cb: &Callback, inp: &[u8]) -> JResult0 {
+    for &ch in inp {
+        let pos = parser.pos;
+        if let Err(e) = parse_char(parser, cb, ch) {
+            let e = e.at(pos).expecting(parser);
+            if !recovering(parser) || !is_input_error(e.kind) {
+                return Err(e);
+            }
+            recover(parser, cb, &e, ch)?;
+            parser.pos = advance(pos, ch);
+        }
+    }
+    Ok(())
+}
+
+const RS : u8 = 0x1e;
+
+fn recovering(parser: &Parser) -> bool {
+    parser.config.recover_errors || parser.config.json_seq
+}
+
+/* errors in the input, as opposed to errors from the callback or the system */
+fn is_input_error(kind: ErrorKind) -> bool {
+    !matches!(kind, ErrorKind::NoMemory | ErrorKind::Callback | ErrorKind::Io | ErrorKind::Custom)
+}
+
+/* errors about a character not allowed where it was found */
+fn is_syntax_error(kind: ErrorKind) -> bool {
+    matches!(kind, ErrorKind::BadChar | ErrorKind::UnexpectedChar | ErrorKind::CommentNotAllowed
+                 | ErrorKind::PopEmpty | ErrorKind::PopUnexpectedMode | ErrorKind::CommaOutOfStructure
+                 | ErrorKind::UnicodeMissingLowSurrogate | ErrorKind::PrematureEof(_))
+}
+
+/* position after a byte */
+fn advance(pos: Position, ch: u8) -> Position {
+    let mut next = pos;
+    next.offset += 1;
+    if ch == b'\n' {
+        next.line += 1;
+        next.column = 1;
+    } else if ch & 0xc0 != 0x80 {
+        next.column += 1;
+    }
+    next
+}
 
-        let next_class = try!(get_next_class(&mut parser, ch));
+fn is_string_state(st: S) -> bool {
+    matches!(st, S::_S | S::E0 | S::E1 | S::U1 | S::U2 | S::U3 | S::U4 | S::D1 | S::D2)
+}
 
-        let next_class_num : u8 = unsafe { mem::transmute(next_class) };
-        let parser_state_num : u8 = unsafe { mem::transmute(parser.state) };
-        let next_state = STATE_TRANS[parser_state_num as usize][next_class_num as usize];
+/* character ending a document whatever its nesting: the record separator of
+ * JSON text sequences, and the newline of JSON Lines */
+fn is_record_end(parser: &Parser, ch: u8) -> bool {
+    if parser.config.json_seq {
+        ch == RS
+    } else {
+        parser.config.json_lines && ch == b'\n'
+    }
+}
 
-        let buffer_policy = BUFFER_POLICY_TABLE[parser_state_num as usize][next_class_num as usize];
-        if next_state == S::__ {
-            return Err(JError::UNEXPECTED_CHAR);
+/* report an error, and skip the input from the character that caused it */
+fn recover(parser: &mut Parser, cb: &Callback, e: &JError, ch: u8) -> JResult0 {
+    // a value is complete, but still waiting in the buffer, unless the
+    // error is about the value itself. a number followed by a record
+    // separator may be truncated, and is dropped (RFC 7464)
+    let complete = is_complete_state(parser.state);
+    let truncated = parser.config.json_seq && ch == RS && parser.state != S::OK;
+    if complete && ends_token(e.kind) && !truncated {
+        do_buffer(parser, cb)?;
+    }
+    // the string the error is in goes on, unless the error is on its closing quote
+    let in_string = is_string_state(parser.state);
+    let quote = if in_string && ch != parser.quote { Some(parser.quote) } else { None };
+    parser.expecting_key = false;
+    parser.utf8_multibyte_left = 0;
+    parser.unicode_multi = 0;
+    parser.jtype = None;
+    parser.buffer.clear();
+    parser.quote = b'"';
+    let start = e.pos.unwrap_or(parser.pos);
+    let span = Span { start, end: advance(start, ch) };
+    if is_record_end(parser, ch) {
+        report_error(parser, cb, e, span)?;
+        close_containers(parser, cb)?;
+        parser.separated = true;
+    } else if quote.is_none() && ends_value(parser, ch) {
+        // the comma or closing bracket is taken as it comes after the broken value
+        parser.state = S::OK;
+        report_error(parser, cb, e, span)?;
+        parse_char(parser, cb, ch)?;
+    } else if complete && starts_key(parser, ch) {
+        // a member after a complete value is taken as if the comma was missing
+        parser.state = S::_K;
+        parser.expecting_key = true;
+        report_error(parser, cb, e, span)?;
+        parse_char(parser, cb, ch)?;
+    } else {
+        let mut skip = Skip { depth: 0, quote, escape: false };
+        match ch {
+            _ if in_string => (),
+            b'[' | b'{' => skip.depth = 1,
+            b'"' => skip.quote = Some(ch),
+            b'\'' if json5(parser) => skip.quote = Some(ch),
+            _ => (),
         }
+        parser.skip = Some(skip);
+        report_error(parser, cb, e, span)?;
+    }
+    Ok(())
+}
 
-        // add char to buffer
-        if buffer_policy > 0 {
-            if buffer_policy > 2 {
-                try!(buffer_push_escape(&mut parser, ch))
-            } else {
-                try!(buffer_push(&mut parser, ch))
+/* errors on the character after a token, which leave the token complete */
+fn ends_token(kind: ErrorKind) -> bool {
+    matches!(kind, ErrorKind::BadChar | ErrorKind::UnexpectedChar | ErrorKind::Utf8)
+}
+
+/* quote starting a key in the innermost open container */
+fn starts_key(parser: &Parser, ch: u8) -> bool {
+    parser.stack.last() == Some(&StackMode::Object) && (ch == b'"' || (ch == b'\'' && json5(parser)))
+}
+
+/* comma or closing bracket of the innermost open container */
+fn ends_value(parser: &Parser, ch: u8) -> bool {
+    matches!((ch, parser.stack.last()),
+             (b',', Some(_)) | (b']', Some(&StackMode::Array)) | (b'}', Some(&StackMode::Object)))
+}
+
+/* close the containers left open when a broken document is given up, so
+ * that their start events still get an end */
+fn close_containers(parser: &mut Parser, cb: &Callback) -> JResult0 {
+    let span = Span { start: parser.pos, end: parser.pos };
+    parser.state = S::GO;
+    while let Some(mode) = parser.stack.pop() {
+        let jev = if mode == StackMode::Array { Jev::ArrayEnd } else { Jev::ObjectEnd };
+        cb(jev, None, span)?;
+    }
+    Ok(())
+}
+
+fn report_error(parser: &mut Parser, cb: &Callback, e: &JError, span: Span) -> JResult0 {
+    if let Some(ref mut errors) = parser.errors {
+        errors.push((e.kind, span));
+    }
+    let msg = e.kind.to_string().into_bytes();
+    cb(Jev::Error, Some(&msg), span)
+}
+
+/* follow the input skipped after an error, and tell if the parsing
+ * resumes at this character */
+fn resume(parser: &mut Parser, cb: &Callback, ch: u8) -> JResult<bool> {
+    if is_record_end(parser, ch) {
+        parser.skip = None;
+        close_containers(parser, cb)?;
+        return Ok(true);
+    }
+    if !parser.config.recover_errors {
+        return Ok(false);
+    }
+    let json5 = json5(parser);
+    let inside = !parser.stack.is_empty();
+    let documents = multi_document(parser) && !parser.config.json_seq && !parser.config.json_lines;
+    let resume_state = {
+        let skip = match parser.skip {
+            Some(ref mut skip) => skip,
+            None => return Ok(true),
+        };
+        if let Some(quote) = skip.quote {
+            if skip.escape {
+                skip.escape = false;
+            } else if ch == b'\\' {
+                skip.escape = true;
+            } else if ch == quote {
+                skip.quote = None;
             }
+            return Ok(false);
         }
+        match ch {
+            b'"' => { skip.quote = Some(ch); None },
+            b'\'' if json5 => { skip.quote = Some(ch); None },
+            b'[' | b'{' => { skip.depth += 1; None },
+            b']' | b'}' if skip.depth > 0 => { skip.depth -= 1; None },
+            b']' | b'}' | b',' if inside && skip.depth == 0 => Some(S::OK),
+            b' ' | b'\t' | b'\r' | b'\n' if documents && !inside && skip.depth == 0 => Some(S::GO),
+            _ => None,
+        }
+    };
+    match resume_state {
+        Some(st) => {
+            parser.skip = None;
+            parser.state = st;
+            Ok(true)
+        },
+        None => Ok(false),
+    }
+}
+
+const UTF8_BOM : [u8; 3] = [0xef, 0xbb, 0xbf];
 
-        // move to the next level
-        let next_state_num : u8 = unsafe { mem::transmute(next_state) };
-        if (next_state_num & 0x80) != 0 {
-            try!(do_action(&mut parser, cb, next_state))
+/* skip the UTF-8 byte order mark at the very beginning of the input */
+fn skip_bom(parser: &mut Parser, ch: u8) -> JResult<bool> {
+    if parser.bom >= UTF8_BOM.len() || parser.pos.offset != parser.bom || parser.config.strict {
+        return Ok(false);
+    }
+    if ch == UTF8_BOM[parser.bom] {
+        parser.bom += 1;
+        parser.pos.offset += 1;
+        Ok(true)
+    } else if parser.bom > 0 {
+        Err(JError::new(ErrorKind::Utf8))
+    } else {
+        parser.bom = UTF8_BOM.len();
+        Ok(false)
+    }
+}
+
+fn parse_char(parser: &mut Parser, cb: &Callback, ch: u8) -> JResult0 {
+    if skip_bom(parser, ch)? {
+        return Ok(());
+    }
+    if parser.skip.is_some() && !resume(parser, cb, ch)? {
+        parser.pos = advance(parser.pos, ch);
+        return Ok(());
+    }
+    let next_class = get_next_class(parser, ch)?;
+
+    let next_class_num : u8 = unsafe { mem::transmute(next_class) };
+    let parser_state_num : u8 = unsafe { mem::transmute(parser.state) };
+    let next_state = STATE_TRANS[parser_state_num as usize][next_class_num as usize];
+
+    let buffer_policy = BUFFER_POLICY_TABLE[parser_state_num as usize][next_class_num as usize];
+    if next_state == S::__ {
+        if parser.state == S::D1 || parser.state == S::D2 {
+            return Err(JError::new(ErrorKind::UnicodeMissingLowSurrogate));
+        }
+        return Err(JError::new(ErrorKind::UnexpectedChar));
+    }
+    if multi_document(parser) {
+        check_separator(parser, next_class, next_state)?
+    }
+
+    // track the position after this char and the span of the current token
+    let next_pos = advance(parser.pos, ch);
+    if is_token_state(next_state) {
+        if !is_token_state(parser.state) {
+            parser.token.start = parser.pos;
+        }
+        parser.token.end = next_pos;
+    }
+
+    // add char to buffer
+    if buffer_policy == 3 {
+        if parser.config.emit_comments {
+            buffer_push(parser, ch)?
+        }
+    } else if buffer_policy > 0 {
+        if buffer_policy == 2 {
+            buffer_push_escape(parser, ch)?
         } else {
-            parser.state = next_state
+            buffer_push(parser, ch)?
         }
     }
+
+    // move to the next level
+    let next_state_num : u8 = unsafe { mem::transmute(next_state) };
+    if (next_state_num & 0x80) != 0 {
+        do_action(parser, cb, next_state)?
+    } else {
+        parser.state = next_state
+    }
+    parser.pos = next_pos;
+    if multi_document(parser) && parser.state == S::OK && parser.stack.is_empty() {
+        document_end(parser, cb)?
+    }
+    if parser.stack.is_empty() && is_separator(parser, next_class) {
+        parser.separated = true;
+    }
     Ok(())
 }
 
-pub fn parse_buf<T: Read>(mut parser: Parser, cb: &Callback, inp: &mut T) -> Result<(),JError> {
-    let inputbuf = &mut [];
-    match inp.read(inputbuf) {
-        Err(e) => Err(JError::IOERR(e)),
-        Ok(n)  => parse_data(parser, cb, inputbuf),
+fn multi_document(parser: &Parser) -> bool {
+    let config = &parser.config;
+    config.multi_document || config.json_lines || config.concatenated || config.json_seq
+}
+
+/* character separating documents in the current mode */
+fn is_separator(parser: &Parser, class: C) -> bool {
+    if parser.config.json_seq {
+        class == C::Rs
+    } else if parser.config.json_lines {
+        class == C::Nl
+    } else {
+        matches!(class, C::Space | C::Nl | C::White)
+    }
+}
+
+/* check the document starting is separated from the previous one as
+ * required by the mode, and that JSON Lines documents stay on one line */
+fn check_separator(parser: &Parser, class: C, next_state: S) -> JResult0 {
+    let config = &parser.config;
+    let starting = parser.state == S::GO && !matches!(next_state, S::GO | S::CB | S::YB);
+    let required = config.json_seq || (parser.documents > 0 && !config.concatenated);
+    let newline = config.json_lines && class == C::Nl && !parser.stack.is_empty();
+    if (starting && required && !parser.separated) || newline {
+        Err(JError::new(ErrorKind::DocumentSeparator))
+    } else {
+        Ok(())
+    }
+}
+
+/* end of a top level value in multi document mode, ready for the next one */
+fn document_end(parser: &mut Parser, cb: &Callback) -> JResult0 {
+    do_buffer(parser, cb)?;
+    parser.state = S::GO;
+    parser.separated = false;
+    let index = parser.documents.to_string().into_bytes();
+    parser.documents += 1;
+    cb(Jev::DocumentEnd, Some(&index), Span { start: parser.pos, end: parser.pos })
+}
+
+/// parse a whole document from a reader until EOF.
+///
+/// the input is read by chunk of `buffer_initial_size` bytes in a read
+/// buffer kept by the parser, so the memory used doesn't depend on the
+/// input size.
+pub fn parse_buf<T: Read>(parser: &mut Parser, cb: &Callback, inp: &mut T) -> JResult0 {
+    let mut inputbuf = mem::take(&mut parser.readbuf);
+    if inputbuf.is_empty() {
+        inputbuf.resize(cmp::max(parser.config.buffer_initial_size, 1), 0);
+    }
+    let r = parse_read(parser, cb, inp, &mut inputbuf);
+    parser.readbuf = inputbuf;
+    r
+}
+
+fn parse_read<T: Read>(parser: &mut Parser, cb: &Callback, inp: &mut T, inputbuf: &mut [u8]) -> JResult0 {
+    loop {
+        match inp.read(inputbuf) {
+            Ok(0)  => return parse_end(parser, cb),
+            Ok(n)  => parse_data(parser, cb, &inputbuf[..n])?,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(JError::from(e).at(parser.pos)),
+        }
+    }
+}
+
+/// terminate the parsing at the end of the input.
+///
+/// any value still pending in the buffer (a top-level number for example)
+/// is flushed, and the document is checked to be complete.
+pub fn parse_end(parser: &mut Parser, cb: &Callback) -> JResult0 {
+    let pos = parser.pos;
+    match parse_eof(parser, cb).map_err(|e| e.at(pos).expecting(parser)) {
+        Err(ref e) if recovering(parser) && is_input_error(e.kind) => {
+            let pos = e.pos.unwrap_or(pos);
+            report_error(parser, cb, e, Span { start: pos, end: pos })?;
+            close_containers(parser, cb)
+        },
+        r => r,
+    }
+}
+
+fn parse_eof(parser: &mut Parser, cb: &Callback) -> JResult0 {
+    if parser.skip.take().is_some() {
+        return match parser.stack.last() {
+            Some(&mode) if parser.config.recover_errors => Err(JError::new(ErrorKind::PrematureEof(Some(mode)))),
+            _ => close_containers(parser, cb),
+        };
+    }
+    if parser.utf8_multibyte_left > 0 || (parser.bom > 0 && parser.bom < UTF8_BOM.len()) {
+        return Err(JError::new(ErrorKind::Utf8));
+    }
+    let state =
+        if parser.state == S::Y1 {
+            let end = parser.pos;
+            do_comment(parser, cb, end)?;
+            if is_state_above_array(parser.save_state) { S::OK } else { parser.save_state }
+        } else {
+            parser.state
+        };
+    if state == S::GO && parser.config.strict && parser.documents == 0 {
+        return Err(JError::new(ErrorKind::PrematureEof(None)));
+    }
+    if state != S::GO && !is_complete_state(state) {
+        return Err(JError::new(ErrorKind::PrematureEof(parser.stack.last().cloned())));
+    }
+    if let Some(&mode) = parser.stack.last() {
+        return Err(JError::new(ErrorKind::PrematureEof(Some(mode))));
+    }
+    // a number not followed by anything may be truncated (RFC 7464)
+    if parser.config.json_seq && state != S::GO && state != S::OK {
+        return Err(JError::new(ErrorKind::PrematureEof(None)));
+    }
+    do_buffer(parser, cb)?;
+    if state != S::GO {
+        parser.state = S::OK;
+        if multi_document(parser) {
+            document_end(parser, cb)?
+        }
+    }
+    Ok(())
+}
+
+/* the character of a class made of a single character */
+fn class_char(class: C) -> Option<char> {
+    let ch = match class {
+        C::a => 'a', C::b => 'b', C::c => 'c', C::d => 'd', C::e => 'e', C::f => 'f',
+        C::l => 'l', C::n => 'n', C::r => 'r', C::s => 's', C::t => 't', C::u => 'u',
+        C::i => 'i', C::x => 'x', C::y => 'y', C::I => 'I', C::N => 'N',
+        C::Star => '*', C::Slash => '/', C::Backs => '\\',
+        _ => return None,
+    };
+    Some(ch)
+}
+
+/* token accepted by a transition of the current state. whitespace and the
+ * transitions of disabled extensions give nothing */
+fn transition_token(parser: &Parser, class: C, next: S) -> Option<Token> {
+    let enabled = match next {
+        S::CB | S::IS => parser.config.extension(parser.config.allow_c_comments),
+        S::YB => parser.config.extension(parser.config.allow_yaml_comments),
+        S::QB | S::IB | S::HX | S::LD | S::PX => json5(parser),
+        S::TA | S::TO => trailing_commas(parser),
+        S::NA if parser.state == S::M0 => json5(parser),
+        S::IN | S::NA => nan_constants(parser),
+        _ => true,
+    };
+    if !enabled {
+        return None;
+    }
+    let token = match parser.state {
+        S::_S | S::E1 if next == S::SE && parser.quote == b'"' => Token::Quote,
+        S::_S | S::E1 if next == S::SQ && parser.quote == b'\'' => Token::Quote,
+        S::_S | S::E1 => Token::StringChar,
+        S::E0 if matches!(class, C::Squote | C::Nl | C::White) => return if json5(parser) { Some(Token::Escape) } else { None },
+        S::E0 => Token::Escape,
+        S::U1 | S::U2 | S::U3 | S::U4 | S::H1 => Token::HexDigit,
+        S::C2 | S::C3 | S::Y1 if next != S::CE && class != C::Star => return None,
+        _ if matches!(class, C::Space | C::Nl | C::White | C::Rs) => return None,
+        S::GO | S::_V | S::_A => match next {
+            S::KS | S::SP | S::AE | S::OE | S::TA | S::TO | S::CB | S::YB => return transition_close(next),
+            _ => Token::Value,
+        },
+        S::_O | S::_K if matches!(next, S::_S | S::QB | S::IB) => Token::Key,
+        S::M0 => match next {
+            S::Z0 | S::I0 => Token::Digit,
+            S::LD => Token::Dot,
+            _ => return class_char(class).map(Token::Char),
+        },
+        _ => match next {
+            S::DF | S::R1 => Token::Dot,
+            S::DE | S::X1 => Token::Exponent,
+            S::X2 => Token::Sign,
+            S::I0 | S::R2 | S::X3 => Token::Digit,
+            S::H2 => Token::HexDigit,
+            S::_I => Token::IdentChar,
+            _ => return transition_close(next).or_else(|| class_char(class).map(Token::Char)),
+        },
+    };
+    Some(token)
+}
+
+/* token of the transitions ending a value or a container */
+fn transition_close(next: S) -> Option<Token> {
+    match next {
+        S::KS | S::IC => Some(Token::Colon),
+        S::SP => Some(Token::Comma),
+        S::AE | S::TA => Some(Token::ArrayEnd),
+        S::OE | S::TO => Some(Token::ObjectEnd),
+        S::CB | S::YB | S::IS => Some(Token::Comment),
+        _ => None,
+    }
+}
+
+/* tokens accepted in the current state, from its transitions in the table
+ * and the innermost open container */
+fn expected_tokens(parser: &Parser) -> Vec<Token> {
+    let state_num : u8 = unsafe { mem::transmute(parser.state) };
+    if (state_num & 0x80) != 0 {
+        return vec![];
+    }
+    let mut tokens = vec![];
+    for (class_num, &next) in STATE_TRANS[state_num as usize].iter().enumerate() {
+        if next == S::__ {
+            continue;
+        }
+        let class : C = unsafe { mem::transmute(class_num as u8) };
+        let token = match transition_token(parser, class, next) {
+            Some(token) => token,
+            None => continue,
+        };
+        let allowed = match (token, parser.stack.last()) {
+            (Token::ArrayEnd, Some(&StackMode::Array)) | (Token::ObjectEnd, Some(&StackMode::Object)) => true,
+            (Token::ArrayEnd, _) | (Token::ObjectEnd, _) => false,
+            (Token::Comma, None) => false,
+            _ => true,
+        };
+        if allowed && !tokens.contains(&token) {
+            tokens.push(token);
+        }
+    }
+    let complete = match parser.state {
+        S::GO => !parser.config.strict || parser.documents > 0,
+        st => is_complete_state(st),
+    };
+    if complete && parser.stack.is_empty() {
+        tokens.push(Token::End);
+    }
+    tokens
+}
+
+impl Parser {
+    /// feed the next chunk of data to the parser
+    pub fn feed(&mut self, cb: &Callback, data: &[u8]) -> JResult0 {
+        parse_data(self, cb, data)
+    }
+
+    /// signal the end of the input to the parser
+    pub fn finish(&mut self, cb: &Callback) -> JResult0 {
+        parse_end(self, cb)
+    }
+
+    /// position of the next character to be parsed
+    pub fn position(&self) -> Position {
+        self.pos
+    }
+
+    /// absolute byte offset of the next character to be parsed
+    pub fn offset(&self) -> usize {
+        self.pos.offset
+    }
+
+    /// line of the next character to be parsed, starting at 1
+    pub fn line(&self) -> usize {
+        self.pos.line
+    }
+
+    /// column of the next character to be parsed, starting at 1
+    pub fn column(&self) -> usize {
+        self.pos.column
+    }
+
+    /// tokens accepted as the next character, computed from the transitions
+    /// of the current state and the innermost open container. the extensions
+    /// disabled in the configuration are not part of it, and whitespace is
+    /// always accepted between tokens.
+    pub fn expected(&self) -> Vec<Token> {
+        expected_tokens(self)
     }
 }
 
 #[test]
 fn it_works() {
 }
+
+#[cfg(test)]
+fn test_config() -> Config {
+    Config::default()
+}
+
+#[cfg(test)]
+fn collect_chunks(chunks: &[&[u8]]) -> Vec<(Jev, Option<Vec<u8>>)> {
+    collect_config(test_config(), chunks)
+}
+
+#[cfg(test)]
+fn collect_config(config: Config, chunks: &[&[u8]]) -> Vec<(Jev, Option<Vec<u8>>)> {
+    use std::cell::RefCell;
+    let events = RefCell::new(Vec::new());
+    {
+        let cb = |ev: Jev, buf: Option<&Vec<u8>>, _: Span| {
+            events.borrow_mut().push((ev, buf.cloned()));
+            Ok(())
+        };
+        let mut parser = init(config);
+        for chunk in chunks {
+            assert!(parser.feed(&cb, chunk).is_ok());
+        }
+        assert!(parser.finish(&cb).is_ok());
+    }
+    events.into_inner()
+}
+
+#[test]
+fn feed_split_at_every_boundary() {
+    let doc = r#"{"k\u00e9y":[-12.5e3,"\u00e9t\u00e9 \u2603 \ud83d\ude00 café ☃",true,{"n":null}],"b":false}"#;
+    let doc = doc.as_bytes();
+    let whole = collect_chunks(&[doc]);
+    assert!(!whole.is_empty());
+    for i in 0..doc.len() + 1 {
+        let (a, b) = doc.split_at(i);
+        assert!(collect_chunks(&[a, b]) == whole);
+    }
+    let bytewise : Vec<&[u8]> = doc.chunks(1).collect();
+    assert!(collect_chunks(&bytewise) == whole);
+}
+
+#[cfg(test)]
+fn finish_error(doc: &[u8], config: Config) -> Option<ErrorKind> {
+    let cb = |_: Jev, _: Option<&Vec<u8>>, _: Span| Ok(());
+    let mut parser = init(config);
+    parser.feed(&cb, doc).and_then(|_| parser.finish(&cb)).err().map(|e| e.kind)
+}
+
+#[test]
+fn finish_flushes_toplevel_values() {
+    let events = collect_chunks(&[b"4", b"2"]);
+    assert!(events == vec![(Jev::Int, Some(b"42".to_vec()))]);
+    let events = collect_chunks(&[b" -1.5e+3 "]);
+    assert!(events == vec![(Jev::Float, Some(b"-1.5e+3".to_vec()))]);
+    let events = collect_chunks(&[b"true"]);
+    assert!(events == vec![(Jev::True, Some(vec![]))]);
+    assert!(collect_chunks(&[b""]).is_empty());
+}
+
+#[test]
+fn finish_rejects_truncated_documents() {
+    match finish_error(b"{\"a\":", test_config()) {
+        Some(ErrorKind::PrematureEof(Some(StackMode::Object))) => (),
+        _ => panic!("expected premature eof in object"),
+    }
+    match finish_error(b"[1, 2", test_config()) {
+        Some(ErrorKind::PrematureEof(Some(StackMode::Array))) => (),
+        _ => panic!("expected premature eof in array"),
+    }
+    match finish_error(b"tru", test_config()) {
+        Some(ErrorKind::PrematureEof(None)) => (),
+        _ => panic!("expected premature eof at top-level"),
+    }
+    match finish_error(b"-", test_config()) {
+        Some(ErrorKind::PrematureEof(None)) => (),
+        _ => panic!("expected premature eof at top-level"),
+    }
+    let config = test_config().allow_yaml_comments(true);
+    assert!(finish_error(b"[1] # done", config).is_none());
+}
+
+#[cfg(test)]
+struct TestReader<'a> {
+    data: &'a [u8],
+    interrupt: bool,
+}
+
+#[cfg(test)]
+impl<'a> Read for TestReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.interrupt = !self.interrupt;
+        if self.interrupt {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "interrupted"));
+        }
+        if self.data.first() == Some(&b'!') {
+            return Err(io::Error::other("broken"));
+        }
+        let n = cmp::min(buf.len(), self.data.len());
+        buf[..n].copy_from_slice(&self.data[..n]);
+        self.data = &self.data[n..];
+        Ok(n)
+    }
+}
+
+#[test]
+fn parse_buf_reads_until_eof() {
+    use std::cell::RefCell;
+    let doc = b"[1, \"two\", {\"three\": 3.0}, null] ";
+    let events = RefCell::new(Vec::new());
+    {
+        let cb = |ev: Jev, buf: Option<&Vec<u8>>, _: Span| {
+            events.borrow_mut().push((ev, buf.cloned()));
+            Ok(())
+        };
+        let mut parser = init(test_config().with_buffer_initial_size(3));
+        let mut reader = TestReader { data: doc, interrupt: false };
+        assert!(parse_buf(&mut parser, &cb, &mut reader).is_ok());
+    }
+    assert!(events.into_inner() == collect_chunks(&[doc]));
+
+    let cb = |_: Jev, _: Option<&Vec<u8>>, _: Span| Ok(());
+    let mut parser = init(test_config());
+    let mut reader = TestReader { data: b"[1, 2", interrupt: false };
+    match parse_buf(&mut parser, &cb, &mut reader).err().map(|e| e.kind) {
+        Some(ErrorKind::PrematureEof(Some(StackMode::Array))) => (),
+        _ => panic!("expected premature eof"),
+    }
+    let mut parser = init(test_config());
+    let mut reader = TestReader { data: b"!", interrupt: false };
+    match parse_buf(&mut parser, &cb, &mut reader).err().map(|e| e.kind) {
+        Some(ErrorKind::Io) => (),
+        _ => panic!("expected io error"),
+    }
+}
+
+#[test]
+fn config_limits() {
+    let config = test_config().with_max_nesting(2);
+    assert!(finish_error(b"[[1]]", config.clone()).is_none());
+    match finish_error(b"[[[1]]]", config) {
+        Some(ErrorKind::NestingLimit) => (),
+        _ => panic!("expected nesting limit"),
+    }
+    // the container over the limit gets no start event
+    let ev = |jev: Jev, d: &[u8]| (jev, Some(d.to_vec()));
+    let config = test_config().with_max_nesting(1).recover_errors(true);
+    assert!(collect_config(config, &[b"[[1], {}, 2]"])
+            == vec![(Jev::ArrayStart, None), ev(Jev::Error, b"nesting limit reached"), ev(Jev::Error, b"nesting limit reached"),
+                    ev(Jev::Int, b"2"), (Jev::ArrayEnd, None)]);
+    let config = test_config().with_max_data(4);
+    assert!(finish_error(b"[\"abcd\", 1234]", config.clone()).is_none());
+    match finish_error(b"[\"abcde\"]", config.clone()) {
+        Some(ErrorKind::DataLimit) => (),
+        _ => panic!("expected data limit"),
+    }
+    match finish_error(b"12345", config) {
+        Some(ErrorKind::DataLimit) => (),
+        _ => panic!("expected data limit"),
+    }
+    match finish_error(b"[1] /* c */", test_config()) {
+        Some(ErrorKind::CommentNotAllowed) => (),
+        _ => panic!("expected comment not allowed"),
+    }
+    assert!(finish_error(b"[1] /* c */", test_config().allow_c_comments(true)).is_none());
+}
+
+#[cfg(test)]
+fn pos(offset: usize, line: usize, column: usize) -> Position {
+    Position { offset, line, column }
+}
+
+#[test]
+fn positions_and_spans() {
+    use std::cell::RefCell;
+    let doc = "{\"é\": [12,\n  true]}\n";
+    let spans = RefCell::new(Vec::new());
+    let cb = |ev: Jev, _: Option<&Vec<u8>>, span: Span| {
+        spans.borrow_mut().push((ev, span));
+        Ok(())
+    };
+    let mut parser = init(test_config());
+    assert!(parser.feed(&cb, doc.as_bytes()).and_then(|_| parser.finish(&cb)).is_ok());
+    assert!(parser.position() == pos(21, 3, 1));
+    let spans = spans.borrow().clone();
+    assert!(spans[0] == (Jev::ObjectStart, Span { start: pos(0, 1, 1), end: pos(1, 1, 2) }));
+    assert!(spans[1] == (Jev::Key, Span { start: pos(1, 1, 2), end: pos(5, 1, 5) }));
+    assert!(spans[2] == (Jev::ArrayStart, Span { start: pos(7, 1, 7), end: pos(8, 1, 8) }));
+    assert!(spans[3] == (Jev::Int, Span { start: pos(8, 1, 8), end: pos(10, 1, 10) }));
+    assert!(spans[4] == (Jev::True, Span { start: pos(14, 2, 3), end: pos(18, 2, 7) }));
+    assert!(spans[5] == (Jev::ArrayEnd, Span { start: pos(18, 2, 7), end: pos(19, 2, 8) }));
+
+    let mut parser = init(test_config());
+    let err = parser.feed(&cb, b"[1,\n 2 x]").err().unwrap();
+    match err.kind() {
+        ErrorKind::UnexpectedChar => (),
+        _ => panic!("expected unexpected char"),
+    }
+    assert!(err.position() == Some(pos(7, 2, 4)));
+}
+
+#[test]
+fn error_display_and_source() {
+    use std::error::Error;
+    let cb = |_: Jev, _: Option<&Vec<u8>>, _: Span| Ok(());
+    let mut parser = init(test_config());
+    let err = parser.feed(&cb, b"[1,\n ]").err().unwrap();
+    assert!(err.kind() == ErrorKind::UnexpectedChar);
+    assert!(err.to_string() == "unexpected character at line 2, column 2");
+    assert!(err.source().is_none());
+
+    let err = JError::from(io::Error::other("broken"));
+    assert!(err.kind() == ErrorKind::Io);
+    assert!(err.to_string() == "I/O error: broken");
+    assert!(err.source().unwrap().to_string() == "broken");
+    let boxed : Box<dyn Error> = Box::new(err);
+    assert!(boxed.to_string() == "I/O error: broken");
+}
+
+#[test]
+fn comment_events() {
+    let config = test_config().allow_c_comments(true).allow_yaml_comments(true).emit_comments(true);
+    let doc = b"/* a **/ [1/*b*/, 2 # c\n] # d";
+    let events = std::cell::RefCell::new(Vec::new());
+    let cb = |ev: Jev, buf: Option<&Vec<u8>>, span: Span| {
+        events.borrow_mut().push((ev, buf.cloned(), span.start.offset, span.end.offset));
+        Ok(())
+    };
+    let mut parser = init(config);
+    assert!(parser.feed(&cb, doc).and_then(|_| parser.finish(&cb)).is_ok());
+    let c = |s: &str, a, b| (Jev::Comment, Some(s.as_bytes().to_vec()), a, b);
+    let events = events.borrow().clone();
+    assert!(events[0] == c("/* a **/", 0, 8));
+    assert!(events[1] == (Jev::ArrayStart, None, 9, 10));
+    assert!(events[2] == (Jev::Int, Some(b"1".to_vec()), 10, 11));
+    assert!(events[3] == c("/*b*/", 11, 16));
+    assert!(events[4] == (Jev::Int, Some(b"2".to_vec()), 18, 19));
+    assert!(events[5] == c("# c", 20, 23));
+    assert!(events[6] == (Jev::ArrayEnd, None, 24, 25));
+    assert!(events[7] == c("# d", 26, 29));
+    assert!(events.len() == 8);
+}
+
+#[test]
+fn escape_decoding() {
+    let cases : &[(&str, &[u8])] = &[
+        ("\\\"", b"\""),
+        ("\\\\", b"\\"),
+        ("\\/", b"/"),
+        ("\\b", b"\x08"),
+        ("\\f", b"\x0c"),
+        ("\\n", b"\x0a"),
+        ("\\r", b"\x0d"),
+        ("\\t", b"\x09"),
+        ("\\u0000", b"\x00"),
+        ("\\u0041", b"A"),
+        ("\\u007f", b"\x7f"),
+        ("\\u0080", b"\xc2\x80"),
+        ("\\u00E9", b"\xc3\xa9"),
+        ("\\u07ff", b"\xdf\xbf"),
+        ("\\u0800", b"\xe0\xa0\x80"),
+        ("\\u2603", b"\xe2\x98\x83"),
+        ("\\uffff", b"\xef\xbf\xbf"),
+        ("\\ud800\\udc00", b"\xf0\x90\x80\x80"),
+        ("\\uD83D\\uDE00", b"\xf0\x9f\x98\x80"),
+        ("\\udbff\\udfff", b"\xf4\x8f\xbf\xbf"),
+    ];
+    for &(esc, expected) in cases {
+        let doc = format!("{{\"{}\":\"<{}>\"}}", esc, esc);
+        let mut value = b"<".to_vec();
+        value.extend_from_slice(expected);
+        value.push(b'>');
+        let events = vec![(Jev::ObjectStart, None),
+                          (Jev::Key, Some(expected.to_vec())),
+                          (Jev::String, Some(value)),
+                          (Jev::ObjectEnd, None)];
+        assert!(collect_chunks(&[doc.as_bytes()]) == events);
+        let bytewise : Vec<&[u8]> = doc.as_bytes().chunks(1).collect();
+        assert!(collect_chunks(&bytewise) == events);
+    }
+
+    let errors : &[(&str, ErrorKind)] = &[
+        ("\"\\x\"", ErrorKind::UnexpectedChar),
+        ("\"\\U0041\"", ErrorKind::UnexpectedChar),
+        ("\"\\u12g4\"", ErrorKind::UnexpectedChar),
+        ("\"\\u123\"", ErrorKind::UnexpectedChar),
+        ("\"\\ud800\"", ErrorKind::UnicodeMissingLowSurrogate),
+        ("\"\\ud800\\n\"", ErrorKind::UnicodeMissingLowSurrogate),
+        ("\"\\ud800\\u0041\"", ErrorKind::UnicodeMissingLowSurrogate),
+        ("\"\\ud800\\ud800\"", ErrorKind::UnicodeMissingLowSurrogate),
+        ("\"\\udc00\"", ErrorKind::UnicodeUnexpectedLowSurrogate),
+    ];
+    for &(doc, kind) in errors {
+        assert!(finish_error(doc.as_bytes(), test_config()) == Some(kind));
+    }
+}
+
+#[test]
+fn strict_mode_and_bom() {
+    let bom_doc = b"\xef\xbb\xbf[1]";
+    let bytewise : Vec<&[u8]> = bom_doc.chunks(1).collect();
+    assert!(collect_chunks(&bytewise) == collect_chunks(&[b"[1]"]));
+    assert!(finish_error(b"\xef\xbb[1]", test_config()) == Some(ErrorKind::Utf8));
+    assert!(finish_error(b"\xef\xbb", test_config()) == Some(ErrorKind::Utf8));
+    assert!(finish_error(b" \xef\xbb\xbf[1]", test_config()) == Some(ErrorKind::UnexpectedChar));
+
+    let strict = test_config().allow_c_comments(true).allow_yaml_comments(true).strict(true);
+    assert!(finish_error(b"[1, \"\\u00e9\", {\"a\": null}]", strict.clone()).is_none());
+    assert!(finish_error(bom_doc, strict.clone()) == Some(ErrorKind::UnexpectedChar));
+    assert!(finish_error(b"[1] /* c */", strict.clone()) == Some(ErrorKind::CommentNotAllowed));
+    assert!(finish_error(b"[1] # c", strict.clone()) == Some(ErrorKind::CommentNotAllowed));
+    assert!(finish_error(b" ", strict.clone()) == Some(ErrorKind::PrematureEof(None)));
+    assert!(finish_error(b" ", test_config()).is_none());
+}
+
+#[test]
+fn json5_syntax() {
+    let doc = "{unquoted: 'single \\' \"quoted\"', $id_2 :[0x1F, -0XaB, +1, .5, 5., -.5e1, Infinity, -Infinity, NaN, +NaN,],\n\
+               'str': \"a\\\nb\", \"t\": [{},],}";
+    let config = test_config().allow_json5(true);
+    let events = collect_config(config.clone(), &[doc.as_bytes()]);
+    let ev = |jev: Jev, d: &str| (jev, Some(d.as_bytes().to_vec()));
+    let expected = vec![
+        (Jev::ObjectStart, None),
+        ev(Jev::Key, "unquoted"), ev(Jev::String, "single ' \"quoted\""),
+        ev(Jev::Key, "$id_2"), (Jev::ArrayStart, None),
+        ev(Jev::Int, "0x1F"), ev(Jev::Int, "-0XaB"), ev(Jev::Int, "+1"),
+        ev(Jev::Float, ".5"), ev(Jev::Float, "5."), ev(Jev::Float, "-.5e1"),
+        ev(Jev::Float, "Infinity"), ev(Jev::Float, "-Infinity"), ev(Jev::Float, "NaN"), ev(Jev::Float, "+NaN"),
+        (Jev::ArrayEnd, None),
+        ev(Jev::Key, "str"), ev(Jev::String, "ab"),
+        ev(Jev::Key, "t"), (Jev::ArrayStart, None), (Jev::ObjectStart, None), (Jev::ObjectEnd, None), (Jev::ArrayEnd, None),
+        (Jev::ObjectEnd, None),
+    ];
+    assert!(events == expected);
+    let bytewise : Vec<&[u8]> = doc.as_bytes().chunks(1).collect();
+    assert!(collect_config(config.clone(), &bytewise) == expected);
+    assert!(collect_config(config.clone(), &[b"0x10"]) == vec![ev(Jev::Int, "0x10")]);
+
+    // line continuations with any line ending, and comments after identifier keys
+    assert!(collect_config(config.clone(), &[b"['a\\\r\nb', 'c\\\rd', \"e\\\r\\\nf\", '\\\r']"])
+            == vec![(Jev::ArrayStart, None), ev(Jev::String, "ab"), ev(Jev::String, "cd"), ev(Jev::String, "ef"),
+                    ev(Jev::String, ""), (Jev::ArrayEnd, None)]);
+    let comments = config.clone().allow_c_comments(true).emit_comments(true);
+    assert!(collect_config(comments.clone(), &[b"{a/*x*/:1, b /**/ /*y*/ : 2}"])
+            == vec![(Jev::ObjectStart, None), ev(Jev::Key, "a"), ev(Jev::Comment, "/*x*/"), ev(Jev::Int, "1"),
+                    ev(Jev::Key, "b"), ev(Jev::Comment, "/**/"), ev(Jev::Comment, "/*y*/"), ev(Jev::Int, "2"),
+                    (Jev::ObjectEnd, None)]);
+    assert!(finish_error(b"{a/*x*/:1}", config.clone()) == Some(ErrorKind::CommentNotAllowed));
+    assert!(finish_error(b"{a/*x*/}", comments) == Some(ErrorKind::UnexpectedChar));
+    assert!(finish_error(b"['a\\\tb']", config.clone()) == Some(ErrorKind::UnexpectedChar));
+    assert!(finish_error(b"['a\\\r\rb']", config.clone()) == Some(ErrorKind::UnexpectedChar));
+
+    for doc in [&b"{a:1}"[..], b"['a']", b"[1,]", b"{\"a\":1,}", b"[0x1]", b"[+1]", b"[.5]", b"[5.]",
+                b"[Infinity]", b"[NaN]", b"[\"\\'\"]", b"[\"a\\\nb\"]", b"[\"a\\\r\nb\"]"].iter() {
+        assert!(finish_error(doc, test_config()) == Some(ErrorKind::UnexpectedChar));
+        assert!(finish_error(doc, config.clone().strict(true)) == Some(ErrorKind::UnexpectedChar));
+        assert!(finish_error(doc, config.clone()).is_none());
+    }
+    for doc in [&b"[,]"[..], b"{,}", b"[1,,]", b"{\"a\":]", b"{a}", b"{1a:1}", b"[0x]", b"[0xg]", b"[.]",
+                b"[-.]", b"[Infinit]", b"[Nan]", b"['a\"]", b"[\"a']", b"[1.0.]"].iter() {
+        assert!(finish_error(doc, config.clone()).is_some());
+    }
+}
+
+#[test]
+fn trailing_commas_option() {
+    let config = test_config().allow_trailing_commas(true);
+    let cases : &[(&[u8], &[u8])] = &[
+        (b"[1,2,]", b"[1,2]"),
+        (b"[1, 2 , ]", b"[1,2]"),
+        (b"{\"a\":1,}", b"{\"a\":1}"),
+        (b"{\"a\":[true,],\"b\":{\"c\":null,},}", b"{\"a\":[true],\"b\":{\"c\":null}}"),
+        (b"[[],{},\"s\",1.5,]", b"[[],{},\"s\",1.5]"),
+    ];
+    for &(with, without) in cases {
+        assert!(collect_config(config.clone(), &[with]) == collect_chunks(&[without]));
+        assert!(finish_error(with, test_config()) == Some(ErrorKind::UnexpectedChar));
+        assert!(finish_error(with, config.clone().strict(true)) == Some(ErrorKind::UnexpectedChar));
+    }
+    for doc in [&b"[,]"[..], b"{,}", b"[1,,]", b"{\"a\":1,,}", b"{\"a\":]", b"{\"a\",}", b"[1],"].iter() {
+        assert!(finish_error(doc, config.clone()).is_some());
+    }
+    assert!(finish_error(b"{a:1,}", config.clone()) == Some(ErrorKind::UnexpectedChar));
+}
+
+#[test]
+fn nan_infinity() {
+    let config = test_config().allow_nan_infinity(true);
+    let events = collect_config(config.clone(), &[b"{\"a\": [NaN, Infinity,-Infinity], \"b\": NaN}"]);
+    let ev = |jev: Jev, d: &[u8]| (jev, Some(d.to_vec()));
+    assert!(events == vec![(Jev::ObjectStart, None), ev(Jev::Key, b"a"), (Jev::ArrayStart, None),
+                           ev(Jev::Float, b"NaN"), ev(Jev::Float, b"Infinity"), ev(Jev::Float, b"-Infinity"),
+                           (Jev::ArrayEnd, None), ev(Jev::Key, b"b"), ev(Jev::Float, b"NaN"), (Jev::ObjectEnd, None)]);
+    assert!(collect_config(config.clone(), &[b"-Infinity"]) == vec![ev(Jev::Float, b"-Infinity")]);
+
+    for doc in [&b"[NaN]"[..], b"[Infinity]", b"[-Infinity]"].iter() {
+        assert!(finish_error(doc, test_config()) == Some(ErrorKind::UnexpectedChar));
+        assert!(finish_error(doc, config.clone().strict(true)) == Some(ErrorKind::UnexpectedChar));
+    }
+    for doc in [&b"[Infinit]"[..], b"[Infinityy]", b"[nan]", b"[NAN]", b"[+Infinity]", b"[inf]"].iter() {
+        assert!(finish_error(doc, config.clone()).is_some());
+    }
+    assert!(finish_error(b"Na", config.clone()) == Some(ErrorKind::PrematureEof(None)));
+    assert!(finish_error(b"[-NaN]", config.clone()) == Some(ErrorKind::UnexpectedChar));
+    assert!(collect_config(test_config().allow_json5(true), &[b"-NaN"]) == vec![ev(Jev::Float, b"-NaN")]);
+}
+
+#[test]
+fn multi_document_stream() {
+    let config = test_config().multi_document(true);
+    let ev = |jev: Jev, d: &[u8]| (jev, Some(d.to_vec()));
+    let doc : &[u8] = b"{\"a\":1}\n[true] 12\n\n\"s\"\t-1.5";
+    let expected = vec![(Jev::ObjectStart, None), ev(Jev::Key, b"a"), ev(Jev::Int, b"1"), (Jev::ObjectEnd, None),
+                        ev(Jev::DocumentEnd, b"0"),
+                        (Jev::ArrayStart, None), ev(Jev::True, b""), (Jev::ArrayEnd, None), ev(Jev::DocumentEnd, b"1"),
+                        ev(Jev::Int, b"12"), ev(Jev::DocumentEnd, b"2"),
+                        ev(Jev::String, b"s"), ev(Jev::DocumentEnd, b"3"),
+                        ev(Jev::Float, b"-1.5"), ev(Jev::DocumentEnd, b"4")];
+    assert!(collect_config(config.clone(), &[doc]) == expected);
+    let bytes : Vec<&[u8]> = doc.chunks(1).collect();
+    assert!(collect_config(config.clone(), &bytes) == expected);
+
+    assert!(collect_config(config.clone(), &[b""]).is_empty());
+    assert!(collect_config(config.clone(), &[b" \n \n"]).is_empty());
+    assert!(collect_config(config.clone().strict(true), &[b"1\n2\n"]).len() == 4);
+    assert!(finish_error(b"\n", config.clone().strict(true)) == Some(ErrorKind::PrematureEof(None)));
+    assert!(finish_error(b"1 2", test_config()) == Some(ErrorKind::UnexpectedChar));
+    assert!(finish_error(b"[1]\n[", config.clone()) == Some(ErrorKind::PrematureEof(Some(StackMode::Array))));
+    assert!(finish_error(b"[1]]", config.clone()) == Some(ErrorKind::UnexpectedChar));
+    assert!(finish_error(b"[1][2]", config) == Some(ErrorKind::DocumentSeparator));
+}
+
+#[test]
+fn json_lines_stream() {
+    let config = test_config().json_lines(true);
+    let events = collect_config(config.clone(), &[b"{\"a\":[1, 2]}\n3\n\n\"x\"\n"]);
+    let ends : Vec<_> = events.iter().filter(|e| e.0 == Jev::DocumentEnd).collect();
+    assert!(ends.len() == 3);
+    assert!(finish_error(b"1 2", config.clone()) == Some(ErrorKind::DocumentSeparator));
+    assert!(finish_error(b"[1] [2]", config.clone()) == Some(ErrorKind::DocumentSeparator));
+    assert!(finish_error(b"[1,\n2]", config.clone()) == Some(ErrorKind::DocumentSeparator));
+    assert!(finish_error(b"{\"a\":\n1}\n", config.clone()) == Some(ErrorKind::DocumentSeparator));
+    assert!(finish_error(b"[1]\r\n[2]\r\n", config.clone()).is_none());
+    let config = config.allow_yaml_comments(true).emit_comments(true);
+    assert!(finish_error(b"1 # one\n2 # two", config.clone()).is_none());
+    assert!(finish_error(b"1# one\n2", config).is_none());
+}
+
+#[test]
+fn concatenated_documents() {
+    let config = test_config().concatenated(true);
+    let ev = |jev: Jev, d: &[u8]| (jev, Some(d.to_vec()));
+    let events = collect_config(config.clone(), &[b"{}{\"a\":true}[]\"s\"null 1 2"]);
+    assert!(events == vec![(Jev::ObjectStart, None), (Jev::ObjectEnd, None), ev(Jev::DocumentEnd, b"0"),
+                           (Jev::ObjectStart, None), ev(Jev::Key, b"a"), ev(Jev::True, b""), (Jev::ObjectEnd, None),
+                           ev(Jev::DocumentEnd, b"1"),
+                           (Jev::ArrayStart, None), (Jev::ArrayEnd, None), ev(Jev::DocumentEnd, b"2"),
+                           ev(Jev::String, b"s"), ev(Jev::DocumentEnd, b"3"),
+                           ev(Jev::Null, b""), ev(Jev::DocumentEnd, b"4"),
+                           ev(Jev::Int, b"1"), ev(Jev::DocumentEnd, b"5"),
+                           ev(Jev::Int, b"2"), ev(Jev::DocumentEnd, b"6")]);
+    assert!(finish_error(b"1[]", config) == Some(ErrorKind::UnexpectedChar));
+}
+
+#[test]
+fn json_text_sequences() {
+    let config = test_config().json_seq(true);
+    let ev = |jev: Jev, d: &[u8]| (jev, Some(d.to_vec()));
+    let events = collect_config(config.clone(), &[b"\x1e{\"a\":1}\n\x1e[true]\n\x1e\"s\"\n\x1e2\n"]);
+    assert!(events == vec![(Jev::ObjectStart, None), ev(Jev::Key, b"a"), ev(Jev::Int, b"1"), (Jev::ObjectEnd, None),
+                           ev(Jev::DocumentEnd, b"0"),
+                           (Jev::ArrayStart, None), ev(Jev::True, b""), (Jev::ArrayEnd, None), ev(Jev::DocumentEnd, b"1"),
+                           ev(Jev::String, b"s"), ev(Jev::DocumentEnd, b"2"),
+                           ev(Jev::Int, b"2"), ev(Jev::DocumentEnd, b"3")]);
+
+    // corrupted and truncated records are skipped up to the next separator
+    let doc : &[u8] = b"\x1e[1,\x1e{\"a\":x}\n\x1e\"ok\"\n\x1e[2]\n[3]\n\x1e4";
+    let error = |msg: &str| ev(Jev::Error, msg.as_bytes());
+    let expected = vec![(Jev::ArrayStart, None), ev(Jev::Int, b"1"), error("unexpected character"), (Jev::ArrayEnd, None),
+                        (Jev::ObjectStart, None), ev(Jev::Key, b"a"), error("unexpected character"), (Jev::ObjectEnd, None),
+                        ev(Jev::String, b"ok"), ev(Jev::DocumentEnd, b"0"),
+                        (Jev::ArrayStart, None), ev(Jev::Int, b"2"), (Jev::ArrayEnd, None), ev(Jev::DocumentEnd, b"1"),
+                        error("missing separator between documents"),
+                        error("premature end of input")];
+    assert!(collect_config(config.clone(), &[doc]) == expected);
+    let bytes : Vec<&[u8]> = doc.chunks(1).collect();
+    assert!(collect_config(config.clone(), &bytes) == expected);
+
+    // a number directly followed by a separator may be truncated, and is dropped
+    assert!(collect_config(config.clone(), &[b"\x1e1\x1e2\n"])
+            == vec![error("unexpected character"), ev(Jev::Int, b"2"), ev(Jev::DocumentEnd, b"0")]);
+    assert!(collect_config(config.clone(), &[b"\x1etrue\x1e1 \x1e"])
+            == vec![ev(Jev::True, b""), ev(Jev::DocumentEnd, b"0"), ev(Jev::Int, b"1"), ev(Jev::DocumentEnd, b"1")]);
+    assert!(collect_config(config.clone(), &[b"\x1e[1, \xff]\n\x1e3\n"]).last() == Some(&ev(Jev::DocumentEnd, b"0")));
+    assert!(finish_error(b"\x1e1\n", test_config()) == Some(ErrorKind::BadChar));
+    assert!(finish_error(b"[1]]", config).is_none());
+}
+
+#[test]
+fn recover_errors() {
+    use std::cell::RefCell;
+    let config = test_config().recover_errors(true);
+    let ev = |jev: Jev, d: &[u8]| (jev, Some(d.to_vec()));
+    let error = |msg: &str| ev(Jev::Error, msg.as_bytes());
+
+    let events = collect_config(config.clone(), &[b"[1, x, [2, {\"a\" 3}], \"s\" \"t\", true]"]);
+    assert!(events == vec![(Jev::ArrayStart, None), ev(Jev::Int, b"1"), error("unexpected character"),
+                           (Jev::ArrayStart, None), ev(Jev::Int, b"2"), (Jev::ObjectStart, None), ev(Jev::Key, b"a"),
+                           error("unexpected character"), (Jev::ObjectEnd, None), (Jev::ArrayEnd, None),
+                           ev(Jev::String, b"s"), error("unexpected character"), ev(Jev::True, b""), (Jev::ArrayEnd, None)]);
+
+    // brackets and strings are followed while skipping
+    let events = collect_config(config.clone(), &[b"{\"a\": -[1, \"]\"], \"b\": 2}"]);
+    assert!(events == vec![(Jev::ObjectStart, None), ev(Jev::Key, b"a"), error("unexpected character"),
+                           ev(Jev::Key, b"b"), ev(Jev::Int, b"2"), (Jev::ObjectEnd, None)]);
+    assert!(collect_config(config.clone(), &[b"[\"a\\x],\", 1]"])
+            == vec![(Jev::ArrayStart, None), error("unexpected character"), ev(Jev::Int, b"1"), (Jev::ArrayEnd, None)]);
+    assert!(collect_config(config.clone(), &[b"[1}"])
+            == vec![(Jev::ArrayStart, None), ev(Jev::Int, b"1"), error("closing bracket doesn't match the opening bracket"),
+                    error("premature end of input in an array"), (Jev::ArrayEnd, None)]);
+    assert!(collect_config(config.clone(), &[b"[1, x"])
+            == vec![(Jev::ArrayStart, None), ev(Jev::Int, b"1"), error("unexpected character"),
+                    error("premature end of input in an array"), (Jev::ArrayEnd, None)]);
+
+    // a mismatched closing bracket leaves its container open
+    assert!(collect_config(config.clone(), &[b"[1, 2}, 3]"])
+            == vec![(Jev::ArrayStart, None), ev(Jev::Int, b"1"), ev(Jev::Int, b"2"),
+                    error("closing bracket doesn't match the opening bracket"), ev(Jev::Int, b"3"), (Jev::ArrayEnd, None)]);
+    assert!(collect_config(config.clone(), &[b"{\"a\": [1}], \"b\": 2}"])
+            == vec![(Jev::ObjectStart, None), ev(Jev::Key, b"a"), (Jev::ArrayStart, None), ev(Jev::Int, b"1"),
+                    error("closing bracket doesn't match the opening bracket"), (Jev::ArrayEnd, None),
+                    ev(Jev::Key, b"b"), ev(Jev::Int, b"2"), (Jev::ObjectEnd, None)]);
+
+    // an error stands in place of the value it broke, the separator after it is kept
+    assert!(collect_config(config.clone(), &[b"{\"a\": x, \"b\":2}"])
+            == vec![(Jev::ObjectStart, None), ev(Jev::Key, b"a"), error("unexpected character"),
+                    ev(Jev::Key, b"b"), ev(Jev::Int, b"2"), (Jev::ObjectEnd, None)]);
+    assert!(collect_config(config.clone(), &[b"{\"a\": }"])
+            == vec![(Jev::ObjectStart, None), ev(Jev::Key, b"a"), error("unexpected character"), (Jev::ObjectEnd, None)]);
+    assert!(collect_config(config.clone(), &[b"[1,, tru]"])
+            == vec![(Jev::ArrayStart, None), ev(Jev::Int, b"1"), error("unexpected character"),
+                    error("unexpected character"), (Jev::ArrayEnd, None)]);
+    assert!(collect_config(config.clone(), &[b"[\"\\ud800\", \"x\", 1]"])
+            == vec![(Jev::ArrayStart, None), error("unicode high surrogate not followed by a low surrogate"),
+                    ev(Jev::String, b"x"), ev(Jev::Int, b"1"), (Jev::ArrayEnd, None)]);
+    assert!(collect_config(config.clone(), &[b"[\"\\u12\", 2, 3]"])
+            == vec![(Jev::ArrayStart, None), error("unexpected character"),
+                    ev(Jev::Int, b"2"), ev(Jev::Int, b"3"), (Jev::ArrayEnd, None)]);
+    assert!(collect_config(config.clone(), &[b"{\"a\":1 \"b\":2}"])
+            == vec![(Jev::ObjectStart, None), ev(Jev::Key, b"a"), ev(Jev::Int, b"1"), error("unexpected character"),
+                    ev(Jev::Key, b"b"), ev(Jev::Int, b"2"), (Jev::ObjectEnd, None)]);
+    assert!(collect_config(config.clone().with_max_data(2), &[b"[123456, 2]"])
+            == vec![(Jev::ArrayStart, None), error("data limit reached"), ev(Jev::Int, b"2"), (Jev::ArrayEnd, None)]);
+    assert!(collect_config(config.clone(), &[b"[12x, 1.5e]"])
+            == vec![(Jev::ArrayStart, None), ev(Jev::Int, b"12"), error("unexpected character"),
+                    error("unexpected character"), (Jev::ArrayEnd, None)]);
+    assert!(collect_config(config.clone(), &[b"1 2 3"]) == vec![ev(Jev::Int, b"1"), error("unexpected character")]);
+
+    // errors outside of a container skip to the next document
+    let lines = config.clone().json_lines(true);
+    assert!(collect_config(lines, &[b"{\"a\":\n1}\n2 x 3\n[4]\n"])
+            == vec![(Jev::ObjectStart, None), ev(Jev::Key, b"a"), error("missing separator between documents"),
+                    (Jev::ObjectEnd, None), ev(Jev::Int, b"1"), error("closing bracket without matching opening bracket"),
+                    ev(Jev::Int, b"2"), ev(Jev::DocumentEnd, b"0"), error("unexpected character"),
+                    (Jev::ArrayStart, None), ev(Jev::Int, b"4"), (Jev::ArrayEnd, None), ev(Jev::DocumentEnd, b"1")]);
+    assert!(collect_config(config.clone().json_lines(true), &[b"12x\n3\n"])
+            == vec![ev(Jev::Int, b"12"), error("unexpected character"), ev(Jev::Int, b"3"), ev(Jev::DocumentEnd, b"0")]);
+    let multi = config.clone().multi_document(true);
+    assert!(collect_config(multi, &[b"1 x 2"])
+            == vec![ev(Jev::Int, b"1"), ev(Jev::DocumentEnd, b"0"), error("unexpected character"),
+                    ev(Jev::Int, b"2"), ev(Jev::DocumentEnd, b"1")]);
+
+    // errors are reported at their position, and callback errors still stop the parsing
+    let errors = RefCell::new(Vec::new());
+    let cb = |ev: Jev, _: Option<&Vec<u8>>, span: Span| {
+        if ev == Jev::Error {
+            errors.borrow_mut().push(span.start);
+        }
+        Ok(())
+    };
+    let mut parser = init(config.clone());
+    assert!(parser.feed(&cb, b"[1,\n x, 2, \xff]").and_then(|_| parser.finish(&cb)).is_ok());
+    assert!(*errors.borrow() == vec![pos(5, 2, 2), pos(11, 2, 8)]);
+    let cb = |ev: Jev, _: Option<&Vec<u8>>, _: Span| {
+        if ev == Jev::Int { Err(JError::new(ErrorKind::Callback)) } else { Ok(()) }
+    };
+    let mut parser = init(config);
+    assert!(parser.feed(&cb, b"[1]").err().map(|e| e.kind) == Some(ErrorKind::Callback));
+}
+
+#[test]
+fn parser_expected() {
+    fn expected(config: Config, doc: &[u8]) -> Vec<Token> {
+        let cb = |_: Jev, _: Option<&Vec<u8>>, _: Span| Ok(());
+        let mut parser = init(config);
+        assert!(parser.feed(&cb, doc).is_ok());
+        parser.expected()
+    }
+    let config = test_config();
+    assert!(expected(config.clone(), b"") == vec![Token::Value, Token::End]);
+    assert!(expected(config.clone().strict(true), b"") == vec![Token::Value]);
+    assert!(expected(config.clone(), b"{") == vec![Token::ObjectEnd, Token::Key]);
+    assert!(expected(config.clone(), b"{\"a\"") == vec![Token::Colon]);
+    assert!(expected(config.clone(), b"{\"a\":") == vec![Token::Value]);
+    assert!(expected(config.clone(), b"{\"a\":[]") == vec![Token::ObjectEnd, Token::Comma]);
+    assert!(expected(config.clone(), b"[true") == vec![Token::ArrayEnd, Token::Comma]);
+    assert!(expected(config.clone(), b"[1.") == vec![Token::Digit]);
+    assert!(expected(config.clone(), b"1e") == vec![Token::Sign, Token::Digit]);
+    assert!(expected(config.clone(), b"0") == vec![Token::Dot, Token::Exponent, Token::End]);
+    assert!(expected(config.clone(), b"[nu") == vec![Token::Char('l')]);
+    assert!(expected(config.clone(), b"\"a") == vec![Token::StringChar, Token::Quote]);
+    assert!(expected(config.clone(), b"\"\\") == vec![Token::Escape]);
+    assert!(expected(config.clone(), b"\"\\u0") == vec![Token::HexDigit]);
+
+    // extensions enabled in the configuration
+    let config = config.allow_c_comments(true).allow_trailing_commas(true);
+    assert!(expected(config.clone(), b"[1,") == vec![Token::Value, Token::ArrayEnd, Token::Comment]);
+    assert!(expected(config.clone(), b"[1,/") == vec![Token::Char('*')]);
+    assert!(expected(config.allow_json5(true), b"{") == vec![Token::ObjectEnd, Token::Key, Token::Comment]);
+
+    let cb = |_: Jev, _: Option<&Vec<u8>>, _: Span| Ok(());
+    let mut parser = init(test_config());
+    let e = parser.feed(&cb, b"{\"a\" 2}").err().unwrap();
+    assert!(e.expected() == [Token::Colon]);
+    assert!(JError::new(ErrorKind::DataLimit).expected().is_empty());
+}
+
@@ -0,0 +1,155 @@
+//! tracking of the location of the events in the document, as JSON
+//! pointers (RFC 6901).
+
+use std::cell::RefCell;
+use std::fmt::Write;
+use {JError, Jev, Span, StackMode};
+
+/// step of a path: the key of an object member, or the index of an array
+/// element
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// location in a document, following the events given to `update`.
+///
+/// after an event, the path is the location of the value the event is part
+/// of: the whole container for its start and end, the member for a key.
+/// for `{"a": [1, 2]}` the pointers are "", "/a", "/a", "/a/0", "/a/1",
+/// "/a" and "".
+#[derive(Clone, Default, Debug)]
+pub struct Path {
+    /* open containers, and whether a member or element was started in each */
+    stack: Vec<(StackMode, bool)>,
+    segments: Vec<Segment>,
+}
+
+impl Path {
+    pub fn new() -> Path {
+        Path::default()
+    }
+
+    /// follow the next event. the path goes back to the root at the end of
+    /// each document. an error event recovered from takes the place of the
+    /// value it broke, and counts as an element in an array.
+    pub fn update(&mut self, jev: Jev, data: Option<&[u8]>) {
+        match jev {
+            Jev::Key => {
+                if let Some(&mut (StackMode::Object, ref mut started)) = self.stack.last_mut() {
+                    if *started {
+                        self.segments.pop();
+                    }
+                    *started = true;
+                    let key = String::from_utf8_lossy(data.unwrap_or(b"")).into_owned();
+                    self.segments.push(Segment::Key(key));
+                }
+            },
+            Jev::ArrayEnd | Jev::ObjectEnd => {
+                if let Some((_, true)) = self.stack.pop() {
+                    self.segments.pop();
+                }
+            },
+            Jev::Comment => (),
+            Jev::DocumentEnd => {
+                self.stack.clear();
+                self.segments.clear();
+            },
+            _ => {
+                if let Some(&mut (StackMode::Array, ref mut started)) = self.stack.last_mut() {
+                    if *started {
+                        if let Some(&mut Segment::Index(ref mut i)) = self.segments.last_mut() {
+                            *i += 1;
+                        }
+                    } else {
+                        *started = true;
+                        self.segments.push(Segment::Index(0));
+                    }
+                }
+                match jev {
+                    Jev::ArrayStart  => self.stack.push((StackMode::Array, false)),
+                    Jev::ObjectStart => self.stack.push((StackMode::Object, false)),
+                    _ => (),
+                }
+            },
+        }
+    }
+
+    /// segments of the path, from the root
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// path as a JSON pointer, "" being the root
+    pub fn pointer(&self) -> String {
+        let mut s = String::new();
+        for segment in &self.segments {
+            s.push('/');
+            match *segment {
+                Segment::Key(ref key) => s.push_str(&key.replace('~', "~0").replace('/', "~1")),
+                Segment::Index(i)     => { let _ = write!(s, "{}", i); },
+            }
+        }
+        s
+    }
+}
+
+/// wrap a callback to also give it the path of each event.
+///
+/// ```
+/// use json_events::{init, track_path, Config};
+///
+/// let cb = track_path(|_, _, _, path| { println!("{}", path.pointer()); Ok(()) });
+/// let mut parser = init(Config::default());
+/// parser.feed(&cb, b"{\"a\": [1, 2]}").unwrap();
+/// parser.finish(&cb).unwrap();
+/// ```
+pub fn track_path<'a, F>(cb: F) -> impl Fn(Jev, Option<&Vec<u8>>, Span) -> Result<(), JError> + 'a
+    where F: Fn(Jev, Option<&Vec<u8>>, Span, &Path) -> Result<(), JError> + 'a
+{
+    let path = RefCell::new(Path::new());
+    move |jev, data, span| {
+        path.borrow_mut().update(jev, data.map(|d| &d[..]));
+        cb(jev, data, span, &path.borrow())
+    }
+}
+
+#[cfg(test)]
+fn pointers(config: ::Config, doc: &str) -> Vec<String> {
+    let out = RefCell::new(vec![]);
+    {
+        let cb = track_path(|_, _, _, path| { out.borrow_mut().push(path.pointer()); Ok(()) });
+        let mut parser = ::init(config);
+        parser.feed(&cb, doc.as_bytes()).and_then(|_| parser.finish(&cb)).unwrap();
+    }
+    out.into_inner()
+}
+
+#[test]
+fn path_tracking() {
+    let config = ::Config::default();
+    assert!(pointers(config.clone(), "{\"a\": [1, 2]}") == vec!["", "/a", "/a", "/a/0", "/a/1", "/a", ""]);
+    assert!(pointers(config.clone(), "[[], {}, [[true]], {\"k\": null}]") ==
+            vec!["", "/0", "/0", "/1", "/1", "/2", "/2/0", "/2/0/0", "/2/0", "/2", "/3", "/3/k", "/3/k", "/3", ""]);
+    assert!(pointers(config.clone(), "{\"a/b\": {\"m~n\": 1, \"\": 2}}") ==
+            vec!["", "/a~1b", "/a~1b", "/a~1b/m~0n", "/a~1b/m~0n", "/a~1b/", "/a~1b/", "/a~1b", ""]);
+    assert!(pointers(config.clone(), "3") == vec![""]);
+
+    // comments don't move, and each document starts from the root
+    let multi = config.clone().multi_document(true).allow_c_comments(true).emit_comments(true);
+    assert!(pointers(multi, "[1 /* c */, 2]\n{\"a\": 3}") ==
+            vec!["", "/0", "/0", "/1", "", "", "", "/a", "/a", "", ""]);
+
+    // recovered errors stand for an element, and broken records are closed
+    let recover = config.clone().recover_errors(true);
+    assert!(pointers(recover, "[1, x, 3]") == vec!["", "/0", "/1", "/2", ""]);
+    let seq = config.json_seq(true);
+    assert!(pointers(seq, "\x1e[1,\n\x1e[2]\n") == vec!["", "/0", "/1", "", "", "/0", "", ""]);
+
+    let mut path = Path::new();
+    path.update(Jev::ArrayStart, None);
+    path.update(Jev::ObjectStart, None);
+    path.update(Jev::Key, Some(b"x"));
+    assert!(path.segments() == &[Segment::Index(0), Segment::Key("x".to_string())][..]);
+}
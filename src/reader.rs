@@ -0,0 +1,211 @@
+use std::cell::RefCell;
+use std::cmp;
+use std::io::{self, Read};
+use {init, parse_data, parse_end, Config, Event, Parser, JError, JResult, Jev, Position, Span};
+
+/// a single undecoded event pulled from an `EventReader`.
+///
+/// the data is only valid until the next call to `next_raw`.
+pub struct RawEvent<'a> {
+    pub jev: Jev,
+    pub data: Option<&'a [u8]>,
+    pub span: Span,
+}
+
+struct PendingEvent {
+    jev: Jev,
+    range: Option<(usize, usize)>,
+    span: Span,
+}
+
+/* events generated by the last bytes fed to the parser */
+struct Pending {
+    events: Vec<PendingEvent>,
+    data: Vec<u8>,
+}
+
+impl Pending {
+    fn push(&mut self, jev: Jev, buf: Option<&Vec<u8>>, span: Span) {
+        let range = buf.map(|b| {
+            let start = self.data.len();
+            self.data.extend_from_slice(b);
+            (start, self.data.len())
+        });
+        self.events.push(PendingEvent { jev, range, span });
+    }
+
+    fn clear(&mut self) {
+        self.events.clear();
+        self.data.clear();
+    }
+}
+
+/// pull parser: read events one at a time from a reader.
+pub struct EventReader<R: Read> {
+    parser: Parser,
+    inp: R,
+    readbuf: Vec<u8>,
+    pos: usize,
+    len: usize,
+    pending: RefCell<Pending>,
+    next: usize,
+    done: bool,
+    /* error met after some events of the last chunk, returned after them */
+    error: Option<JError>,
+}
+
+impl<R: Read> EventReader<R> {
+    pub fn new(config: Config, inp: R) -> EventReader<R> {
+        let size = cmp::max(config.buffer_initial_size, 1);
+        EventReader {
+            parser: init(config),
+            inp,
+            readbuf: vec![0; size],
+            pos: 0,
+            len: 0,
+            pending: RefCell::new(Pending { events: vec![], data: vec![] }),
+            next: 0,
+            done: false,
+            error: None,
+        }
+    }
+
+    /// return the next decoded event, or None at the end of the document
+    pub fn next_event(&mut self) -> JResult<Option<Event<'_>>> {
+        match self.next_raw()? {
+            None     => Ok(None),
+            Some(ev) => Event::from_raw(ev.jev, ev.data).map(Some),
+        }
+    }
+
+    /// return the next raw event, or None at the end of the document
+    pub fn next_raw(&mut self) -> JResult<Option<RawEvent<'_>>> {
+        if self.next >= self.pending.borrow().events.len() {
+            self.next = 0;
+            self.pending.get_mut().clear();
+            if let Err(e) = self.fill() {
+                self.done = true;
+                return Err(e);
+            }
+        }
+
+        let pending : &Pending = self.pending.get_mut();
+        match pending.events.get(self.next) {
+            None => Ok(None),
+            Some(ev) => {
+                self.next += 1;
+                let data = ev.range.map(|(s, e)| &pending.data[s..e]);
+                Ok(Some(RawEvent { jev: ev.jev, data, span: ev.span }))
+            }
+        }
+    }
+
+    /// configuration of the parser
+    pub fn config(&self) -> &Config {
+        &self.parser.config
+    }
+
+    /// position of the next character to be parsed. the input is parsed by
+    /// chunk, so it can be past the events not read yet.
+    pub fn position(&self) -> Position {
+        self.parser.position()
+    }
+
+    /* run the parser on the next chunks of input until at least one event
+     * is generated or the input is exhausted */
+    fn fill(&mut self) -> JResult<()> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        let pending = &self.pending;
+        let cb = |ev: Jev, buf: Option<&Vec<u8>>, span: Span| {
+            pending.borrow_mut().push(ev, buf, span);
+            Ok(())
+        };
+        while !self.done && pending.borrow().events.is_empty() {
+            if self.pos == self.len {
+                self.pos = 0;
+                self.len = match self.inp.read(&mut self.readbuf) {
+                    Ok(n)  => n,
+                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(JError::from(e).at(self.parser.position())),
+                };
+                if self.len == 0 {
+                    self.done = true;
+                    parse_end(&mut self.parser, &cb)?;
+                }
+                continue;
+            }
+            let r = parse_data(&mut self.parser, &cb, &self.readbuf[self.pos..self.len]);
+            self.pos = self.len;
+            if let Err(e) = r {
+                // the events before the error are read first
+                if pending.borrow().events.is_empty() {
+                    return Err(e);
+                }
+                self.done = true;
+                self.error = Some(e);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn reader_pulls_same_events() {
+    let doc = b"{\"a\": [1, 2.5, \"x\"], \"b\": {\"c\": null}, \"d\": true} ";
+    let expected = ::collect_chunks(&[doc]);
+    let mut reader = EventReader::new(Config::default().with_buffer_initial_size(5), &doc[..]);
+    let mut events = vec![];
+    while let Some(ev) = reader.next_raw().ok().unwrap() {
+        events.push((ev.jev, ev.data.map(|d| d.to_vec())));
+    }
+    assert!(events == expected);
+    assert!(reader.next_event().ok().unwrap().is_none());
+}
+
+#[test]
+fn reader_recursive_descent() {
+    fn sum<R: Read>(reader: &mut EventReader<R>) -> i64 {
+        let mut total = 0;
+        loop {
+            match reader.next_event().ok().unwrap() {
+                None                    => return total,
+                Some(Event::ArrayStart) => total += sum(reader),
+                Some(Event::ArrayEnd)   => return total,
+                Some(Event::Int(i))     => total += i,
+                _                       => panic!("unexpected event"),
+            }
+        }
+    }
+    let mut reader = EventReader::new(Config::default(), &b"[1, [2, 3], [[4]], 5]"[..]);
+    assert!(sum(&mut reader) == 15);
+}
+
+#[test]
+fn reader_reports_errors() {
+    let mut reader = EventReader::new(Config::default(), &b"[1, }"[..]);
+    assert!(reader.next_event().ok().unwrap() == Some(Event::ArrayStart));
+    assert!(reader.next_event().ok().unwrap() == Some(Event::Int(1)));
+    match reader.next_event().err().map(|e| e.kind) {
+        Some(::ErrorKind::UnexpectedChar) => (),
+        _ => panic!("expected unexpected char"),
+    }
+    assert!(reader.next_event().ok().unwrap().is_none());
+
+    // the error is returned after the events of its chunk, at its position
+    for size in 1..8 {
+        let mut reader = EventReader::new(Config::default().with_buffer_initial_size(size), &b"[1, 2, }"[..]);
+        let mut events = vec![];
+        let e = loop {
+            match reader.next_raw() {
+                Ok(Some(ev)) => events.push(ev.jev),
+                Ok(None)     => panic!("expected an error"),
+                Err(e)       => break e,
+            }
+        };
+        assert!(events == vec![Jev::ArrayStart, Jev::Int, Jev::Int]);
+        assert!(e.kind() == ::ErrorKind::UnexpectedChar && e.position() == Some(::pos(7, 1, 8)));
+        assert!(reader.next_raw().ok().unwrap().is_none());
+    }
+}
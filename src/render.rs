@@ -0,0 +1,101 @@
+//! rendering of errors for humans, with the line of the input where they
+//! happened.
+
+use std::cmp;
+use {JError, Token};
+
+/* "a", "a or b", "a, b or c" */
+fn alternatives(tokens: &[Token]) -> String {
+    let mut s = String::new();
+    for (i, t) in tokens.iter().enumerate() {
+        if i > 0 {
+            s.push_str(if i == tokens.len() - 1 { " or " } else { ", " });
+        }
+        s.push_str(&t.to_string());
+    }
+    s
+}
+
+/* the character at an offset of the input */
+fn found(input: &[u8], offset: usize) -> String {
+    if offset >= input.len() {
+        return "the end of input".to_string();
+    }
+    let end = cmp::min(offset + 4, input.len());
+    match String::from_utf8_lossy(&input[offset..end]).chars().next() {
+        Some(c) => format!("{:?}", c),
+        None    => "nothing".to_string(),
+    }
+}
+
+impl JError {
+    /// render the error for humans, with the line of the input where it
+    /// happened, a caret under the column, and for syntax errors the tokens
+    /// expected there and what was found instead:
+    ///
+    /// ```text
+    /// error: unexpected character at line 2, column 7
+    ///   |
+    /// 2 |  "b": x
+    ///   |       ^ expected a value, found 'x'
+    /// ```
+    ///
+    /// `input` is the whole input given to the parser.
+    pub fn render(&self, input: &[u8]) -> String {
+        let mut out = format!("error: {}", self);
+        let pos = match self.pos {
+            Some(pos) => pos,
+            None      => return out,
+        };
+        let offset = cmp::min(pos.offset, input.len());
+        let start = input[..offset].iter().rposition(|&c| c == b'\n').map_or(0, |i| i + 1);
+        let end = input[start..].iter().position(|&c| c == b'\n').map_or(input.len(), |i| start + i);
+        let text = String::from_utf8_lossy(&input[start..end]);
+        let mut line = text.trim_end_matches('\r');
+        if start == 0 {
+            line = line.trim_start_matches('\u{feff}');
+        }
+
+        let number = pos.line.to_string();
+        let margin = " ".repeat(number.len());
+        let pad : String = line.chars().take(pos.column - 1).map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+        out.push_str(&format!("\n{} |\n{} | {}\n{} | {}^", margin, number, line, margin, pad));
+        if !self.expected.is_empty() {
+            out.push_str(&format!(" expected {}, found {}", alternatives(&self.expected), found(input, pos.offset)));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+fn render_error(config: ::Config, doc: &str) -> String {
+    let cb = |_: ::Jev, _: Option<&Vec<u8>>, _: ::Span| Ok(());
+    let mut parser = ::init(config);
+    let e = parser.feed(&cb, doc.as_bytes()).and_then(|_| parser.finish(&cb)).err().unwrap();
+    e.render(doc.as_bytes())
+}
+
+#[test]
+fn render_errors() {
+    let config = ::Config::default();
+    assert!(render_error(config.clone(), "{\"a\": 1,\n \"b\": x\n}") ==
+            "error: unexpected character at line 2, column 7\n  |\n2 |  \"b\": x\n  |       ^ expected a value, found 'x'");
+    assert!(render_error(config.clone(), "[1, 2}") ==
+            "error: closing bracket doesn't match the opening bracket at line 1, column 6\n\
+             \x20 |\n1 | [1, 2}\n  |      ^ expected `]`, `,`, `.`, a digit or an exponent, found '}'");
+    assert!(render_error(config.clone(), "{\"k\" 1}") ==
+            "error: unexpected character at line 1, column 6\n  |\n1 | {\"k\" 1}\n  |      ^ expected `:`, found '1'");
+    assert!(render_error(config.clone(), "\t[tru]\r\n") ==
+            "error: unexpected character at line 1, column 6\n  |\n1 | \t[tru]\n  | \t    ^ expected `e`, found ']'");
+    assert!(render_error(config.clone(), "[\"é\u{1}\"]") ==
+            "error: invalid control character at line 1, column 4\n  |\n1 | [\"é\u{1}\"]\n  |    ^ \
+             expected a string character or a closing quote, found '\\u{1}'");
+    assert!(render_error(config.clone(), "{\"a\":[1,\n") ==
+            "error: premature end of input in an array at line 2, column 1\n  |\n2 | \n  | ^ expected a value, found the end of input");
+    assert!(render_error(config.clone().allow_json5(true), "{a:1,}x") ==
+            "error: unexpected character at line 1, column 7\n  |\n1 | {a:1,}x\n  |       ^ expected the end of input, found 'x'");
+
+    let e = JError::new(::ErrorKind::DataLimit).at(::pos(2, 1, 3));
+    assert!(e.render(b"\"abc\"") == "error: data limit reached at line 1, column 3\n  |\n1 | \"abc\"\n  |   ^");
+    assert!(JError::custom("oops").render(b"") == "error: oops");
+}
@@ -0,0 +1,478 @@
+//! serde serialization through the streaming writer.
+//!
+//! externally tagged enums, as `"Variant"` or `{"Variant": value}`, and map
+//! keys are turned into strings when they are integers or chars.
+
+use std::fmt;
+use std::io::Write;
+use serde::ser::{self, Serialize};
+use {ErrorKind, JError, JResult, Jev, JsonWriter, Style};
+
+impl ser::Error for JError {
+    fn custom<T: fmt::Display>(msg: T) -> JError {
+        JError::custom(msg)
+    }
+}
+
+/// serde serializer emitting events to a writer
+pub struct Serializer<W: Write> {
+    writer: JsonWriter<W>,
+}
+
+impl<W: Write> Serializer<W> {
+    pub fn new(out: W) -> Serializer<W> {
+        Serializer::with_style(out, Style::Compact)
+    }
+
+    pub fn with_style(out: W, style: Style) -> Serializer<W> {
+        Serializer { writer: JsonWriter::with_style(out, style) }
+    }
+
+    /// escape all non-ASCII characters in strings and keys
+    pub fn escape_non_ascii(self, escape: bool) -> Serializer<W> {
+        Serializer { writer: self.writer.escape_non_ascii(escape) }
+    }
+
+    /// check that a whole document has been written and flush the output
+    pub fn finish(&mut self) -> JResult<()> {
+        self.writer.finish()
+    }
+
+    /// return the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner()
+    }
+
+    fn event(&mut self, jev: Jev, data: Option<&[u8]>) -> JResult<()> {
+        self.writer.write_event(jev, data)
+    }
+
+    fn float<T: fmt::Debug>(&mut self, finite: bool, v: T) -> JResult<()> {
+        if !finite {
+            return Err(JError::new(ErrorKind::InvalidValue));
+        }
+        self.event(Jev::Float, Some(format!("{:?}", v).as_bytes()))
+    }
+
+    fn variant(&mut self, variant: &str) -> JResult<()> {
+        self.event(Jev::ObjectStart, None)?;
+        self.event(Jev::Key, Some(variant.as_bytes()))
+    }
+}
+
+/// serialize a value as a whole document into a writer
+pub fn to_writer<W: Write, T: ?Sized + Serialize>(out: W, value: &T, style: Style) -> JResult<W> {
+    let mut ser = Serializer::with_style(out, style);
+    value.serialize(&mut ser)?;
+    ser.finish()?;
+    Ok(ser.into_inner())
+}
+
+/// serialize a value as a compact document into a vector
+pub fn to_vec<T: ?Sized + Serialize>(value: &T) -> JResult<Vec<u8>> {
+    to_writer(Vec::new(), value, Style::Compact)
+}
+
+/// serialize a value as a compact document into a string
+pub fn to_string<T: ?Sized + Serialize>(value: &T) -> JResult<String> {
+    to_vec(value).map(|v| String::from_utf8(v).expect("writer output is UTF-8"))
+}
+
+impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
+    type Ok = ();
+    type Error = JError;
+    type SerializeSeq = Compound<'a, W>;
+    type SerializeTuple = Compound<'a, W>;
+    type SerializeTupleStruct = Compound<'a, W>;
+    type SerializeTupleVariant = Compound<'a, W>;
+    type SerializeMap = Compound<'a, W>;
+    type SerializeStruct = Compound<'a, W>;
+    type SerializeStructVariant = Compound<'a, W>;
+
+    fn serialize_bool(self, v: bool) -> JResult<()> {
+        self.event(if v { Jev::True } else { Jev::False }, None)
+    }
+
+    fn serialize_i8(self, v: i8) -> JResult<()> { self.serialize_i64(v as i64) }
+    fn serialize_i16(self, v: i16) -> JResult<()> { self.serialize_i64(v as i64) }
+    fn serialize_i32(self, v: i32) -> JResult<()> { self.serialize_i64(v as i64) }
+    fn serialize_u8(self, v: u8) -> JResult<()> { self.serialize_u64(v as u64) }
+    fn serialize_u16(self, v: u16) -> JResult<()> { self.serialize_u64(v as u64) }
+    fn serialize_u32(self, v: u32) -> JResult<()> { self.serialize_u64(v as u64) }
+
+    fn serialize_i64(self, v: i64) -> JResult<()> {
+        self.event(Jev::Int, Some(v.to_string().as_bytes()))
+    }
+
+    fn serialize_i128(self, v: i128) -> JResult<()> {
+        self.event(Jev::Int, Some(v.to_string().as_bytes()))
+    }
+
+    fn serialize_u64(self, v: u64) -> JResult<()> {
+        self.event(Jev::Int, Some(v.to_string().as_bytes()))
+    }
+
+    fn serialize_u128(self, v: u128) -> JResult<()> {
+        self.event(Jev::Int, Some(v.to_string().as_bytes()))
+    }
+
+    fn serialize_f32(self, v: f32) -> JResult<()> {
+        self.float(v.is_finite(), v)
+    }
+
+    fn serialize_f64(self, v: f64) -> JResult<()> {
+        self.float(v.is_finite(), v)
+    }
+
+    fn serialize_char(self, v: char) -> JResult<()> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> JResult<()> {
+        self.event(Jev::String, Some(v.as_bytes()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> JResult<()> {
+        use serde::ser::SerializeSeq;
+        let mut seq = self.serialize_seq(Some(v.len()))?;
+        for b in v {
+            seq.serialize_element(b)?;
+        }
+        seq.end()
+    }
+
+    fn serialize_none(self) -> JResult<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> JResult<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> JResult<()> {
+        self.event(Jev::Null, None)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> JResult<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> JResult<()> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, value: &T) -> JResult<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _name: &'static str, _index: u32,
+                                                        variant: &'static str, value: &T) -> JResult<()> {
+        self.variant(variant)?;
+        value.serialize(&mut *self)?;
+        self.event(Jev::ObjectEnd, None)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> JResult<Compound<'a, W>> {
+        self.event(Jev::ArrayStart, None)?;
+        Ok(Compound { ser: self, variant: false })
+    }
+
+    fn serialize_tuple(self, len: usize) -> JResult<Compound<'a, W>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> JResult<Compound<'a, W>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, variant: &'static str, _len: usize)
+        -> JResult<Compound<'a, W>> {
+        self.variant(variant)?;
+        self.event(Jev::ArrayStart, None)?;
+        Ok(Compound { ser: self, variant: true })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> JResult<Compound<'a, W>> {
+        self.event(Jev::ObjectStart, None)?;
+        Ok(Compound { ser: self, variant: false })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> JResult<Compound<'a, W>> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, variant: &'static str, _len: usize)
+        -> JResult<Compound<'a, W>> {
+        self.variant(variant)?;
+        self.event(Jev::ObjectStart, None)?;
+        Ok(Compound { ser: self, variant: true })
+    }
+}
+
+/* serializer for arrays and objects, closing the variant object if any */
+pub struct Compound<'a, W: Write + 'a> {
+    ser: &'a mut Serializer<W>,
+    variant: bool,
+}
+
+impl<'a, W: Write> Compound<'a, W> {
+    fn close(self, jev: Jev) -> JResult<()> {
+        self.ser.event(jev, None)?;
+        if self.variant {
+            self.ser.event(Jev::ObjectEnd, None)?;
+        }
+        Ok(())
+    }
+
+    fn field<T: ?Sized + Serialize>(&mut self, key: &str, value: &T) -> JResult<()> {
+        self.ser.event(Jev::Key, Some(key.as_bytes()))?;
+        value.serialize(&mut *self.ser)
+    }
+}
+
+impl<'a, W: Write> ser::SerializeSeq for Compound<'a, W> {
+    type Ok = ();
+    type Error = JError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> JResult<()> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> JResult<()> {
+        self.close(Jev::ArrayEnd)
+    }
+}
+
+impl<'a, W: Write> ser::SerializeTuple for Compound<'a, W> {
+    type Ok = ();
+    type Error = JError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> JResult<()> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> JResult<()> {
+        self.close(Jev::ArrayEnd)
+    }
+}
+
+impl<'a, W: Write> ser::SerializeTupleStruct for Compound<'a, W> {
+    type Ok = ();
+    type Error = JError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> JResult<()> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> JResult<()> {
+        self.close(Jev::ArrayEnd)
+    }
+}
+
+impl<'a, W: Write> ser::SerializeTupleVariant for Compound<'a, W> {
+    type Ok = ();
+    type Error = JError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> JResult<()> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> JResult<()> {
+        self.close(Jev::ArrayEnd)
+    }
+}
+
+impl<'a, W: Write> ser::SerializeMap for Compound<'a, W> {
+    type Ok = ();
+    type Error = JError;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> JResult<()> {
+        key.serialize(MapKeySerializer { ser: &mut *self.ser })
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> JResult<()> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> JResult<()> {
+        self.close(Jev::ObjectEnd)
+    }
+}
+
+impl<'a, W: Write> ser::SerializeStruct for Compound<'a, W> {
+    type Ok = ();
+    type Error = JError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> JResult<()> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> JResult<()> {
+        self.close(Jev::ObjectEnd)
+    }
+}
+
+impl<'a, W: Write> ser::SerializeStructVariant for Compound<'a, W> {
+    type Ok = ();
+    type Error = JError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> JResult<()> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> JResult<()> {
+        self.close(Jev::ObjectEnd)
+    }
+}
+
+/* serializer for map keys: strings, chars and integers, emitted as key events */
+struct MapKeySerializer<'a, W: Write + 'a> {
+    ser: &'a mut Serializer<W>,
+}
+
+fn bad_key<T>() -> JResult<T> {
+    Err(JError::custom("map key must be a string"))
+}
+
+impl<'a, W: Write> MapKeySerializer<'a, W> {
+    fn key<T: fmt::Display>(self, v: T) -> JResult<()> {
+        self.ser.event(Jev::Key, Some(v.to_string().as_bytes()))
+    }
+}
+
+impl<'a, W: Write> ser::Serializer for MapKeySerializer<'a, W> {
+    type Ok = ();
+    type Error = JError;
+    type SerializeSeq = ser::Impossible<(), JError>;
+    type SerializeTuple = ser::Impossible<(), JError>;
+    type SerializeTupleStruct = ser::Impossible<(), JError>;
+    type SerializeTupleVariant = ser::Impossible<(), JError>;
+    type SerializeMap = ser::Impossible<(), JError>;
+    type SerializeStruct = ser::Impossible<(), JError>;
+    type SerializeStructVariant = ser::Impossible<(), JError>;
+
+    fn serialize_str(self, v: &str) -> JResult<()> {
+        self.ser.event(Jev::Key, Some(v.as_bytes()))
+    }
+
+    fn serialize_char(self, v: char) -> JResult<()> { self.key(v) }
+    fn serialize_i8(self, v: i8) -> JResult<()> { self.key(v) }
+    fn serialize_i16(self, v: i16) -> JResult<()> { self.key(v) }
+    fn serialize_i32(self, v: i32) -> JResult<()> { self.key(v) }
+    fn serialize_i64(self, v: i64) -> JResult<()> { self.key(v) }
+    fn serialize_i128(self, v: i128) -> JResult<()> { self.key(v) }
+    fn serialize_u8(self, v: u8) -> JResult<()> { self.key(v) }
+    fn serialize_u16(self, v: u16) -> JResult<()> { self.key(v) }
+    fn serialize_u32(self, v: u32) -> JResult<()> { self.key(v) }
+    fn serialize_u64(self, v: u64) -> JResult<()> { self.key(v) }
+    fn serialize_u128(self, v: u128) -> JResult<()> { self.key(v) }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> JResult<()> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, value: &T) -> JResult<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_bool(self, _v: bool) -> JResult<()> { bad_key() }
+    fn serialize_f32(self, _v: f32) -> JResult<()> { bad_key() }
+    fn serialize_f64(self, _v: f64) -> JResult<()> { bad_key() }
+    fn serialize_bytes(self, _v: &[u8]) -> JResult<()> { bad_key() }
+    fn serialize_none(self) -> JResult<()> { bad_key() }
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> JResult<()> { bad_key() }
+    fn serialize_unit(self) -> JResult<()> { bad_key() }
+    fn serialize_unit_struct(self, _name: &'static str) -> JResult<()> { bad_key() }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _name: &'static str, _index: u32,
+                                                        _variant: &'static str, _value: &T) -> JResult<()> {
+        bad_key()
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> JResult<Self::SerializeSeq> { bad_key() }
+    fn serialize_tuple(self, _len: usize) -> JResult<Self::SerializeTuple> { bad_key() }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> JResult<Self::SerializeTupleStruct> {
+        bad_key()
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize)
+        -> JResult<Self::SerializeTupleVariant> {
+        bad_key()
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> JResult<Self::SerializeMap> { bad_key() }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> JResult<Self::SerializeStruct> {
+        bad_key()
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize)
+        -> JResult<Self::SerializeStructVariant> {
+        bad_key()
+    }
+}
+
+#[cfg(test)]
+use std::collections::BTreeMap;
+
+#[cfg(test)]
+#[derive(Serialize)]
+enum Shape {
+    Empty,
+    Circle(f64),
+    Point(i32, i32),
+    Rect { w: u32, h: u32 },
+}
+
+#[cfg(test)]
+#[derive(Serialize)]
+struct Item {
+    name: String,
+    count: Option<u64>,
+    ratio: f32,
+    shapes: Vec<Shape>,
+    extra: BTreeMap<u8, (bool, char)>,
+}
+
+#[test]
+fn serialize_struct() {
+    let mut extra = BTreeMap::new();
+    extra.insert(7, (true, 'x'));
+    let item = Item {
+        name: "first".to_string(),
+        count: None,
+        ratio: 0.1,
+        shapes: vec![Shape::Empty, Shape::Circle(1.5), Shape::Point(1, -2), Shape::Rect { w: 3, h: 4 }],
+        extra,
+    };
+    let s = to_string(&item).ok().unwrap();
+    assert!(s == r#"{"name":"first","count":null,"ratio":0.1,"shapes":["Empty",{"Circle":1.5},{"Point":[1,-2]},{"Rect":{"w":3,"h":4}}],"extra":{"7":[true,"x"]}}"#);
+
+    let out = to_writer(Vec::new(), &Shape::Point(1, 2), Style::Spaces(2)).ok().unwrap();
+    assert!(out == b"{\n  \"Point\": [\n    1,\n    2\n  ]\n}\n".to_vec());
+}
+
+#[test]
+fn serialize_escapes() {
+    let s = "q\"b\\ \u{1}\u{1f}\n\té☃😀".to_string();
+    let out = to_string(&s).ok().unwrap();
+    assert!(out == "\"q\\\"b\\\\ \\u0001\\u001f\\n\\té☃😀\"");
+
+    let mut ser = Serializer::new(Vec::new()).escape_non_ascii(true);
+    ser::Serialize::serialize(&s, &mut ser).ok().unwrap();
+    ser.finish().ok().unwrap();
+    let out = ser.into_inner();
+    assert!(out == b"\"q\\\"b\\\\ \\u0001\\u001f\\n\\t\\u00e9\\u2603\\ud83d\\ude00\"".to_vec());
+
+    let v : String = ::de::from_slice(::Config::default(), &out).ok().unwrap();
+    assert!(v == s);
+}
+
+#[test]
+fn serialize_errors() {
+    assert!(to_string(&f64::NAN).err().map(|e| e.kind()) == Some(ErrorKind::InvalidValue));
+    let mut m = BTreeMap::new();
+    m.insert(vec![1], 2);
+    assert!(to_string(&m).err().map(|e| e.kind()) == Some(ErrorKind::Custom));
+}
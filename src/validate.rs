@@ -0,0 +1,144 @@
+//! validation reporting all the problems of a document, for linters and
+//! editor integration.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::io::Read;
+use {init, parse_buf, Config, ErrorKind, Jev, Span};
+
+/// how serious a problem is
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Severity {
+    /// the document is invalid
+    Error,
+    /// the document is valid, but probably not as intended
+    Warning,
+}
+
+/// problem found in a document
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Diagnostic {
+    pub span: Span,
+    pub severity: Severity,
+    pub code: ErrorKind,
+    pub message: String,
+}
+
+/* keys of the objects being parsed, to find duplicates */
+struct Keys {
+    stack: Vec<Option<HashSet<Vec<u8>>>>,
+    warnings: Vec<Diagnostic>,
+}
+
+impl Keys {
+    fn event(&mut self, jev: Jev, data: Option<&Vec<u8>>, span: Span) {
+        match jev {
+            Jev::ObjectStart => self.stack.push(Some(HashSet::new())),
+            Jev::ArrayStart  => self.stack.push(None),
+            Jev::ObjectEnd | Jev::ArrayEnd => { self.stack.pop(); },
+            Jev::Key => {
+                let key = data.cloned().unwrap_or_default();
+                if let Some(&mut Some(ref mut keys)) = self.stack.last_mut() {
+                    if keys.contains(&key) {
+                        self.warnings.push(Diagnostic {
+                            span,
+                            severity: Severity::Warning,
+                            code: ErrorKind::DuplicateKey,
+                            message: format!("duplicate key \"{}\"", String::from_utf8_lossy(&key)),
+                        });
+                    } else {
+                        keys.insert(key);
+                    }
+                }
+            },
+            Jev::DocumentEnd => self.stack.clear(),
+            _ => (),
+        }
+    }
+}
+
+/// validate a whole document from a reader, and return all its problems
+/// ordered by position. an empty result means the document is valid.
+///
+/// the parsing goes on after errors as with `Config::recover_errors`, so
+/// each malformed value gets its own diagnostic. keys appearing twice in
+/// an object are reported as warnings, also after an error.
+pub fn validate<R: Read>(config: Config, mut inp: R) -> Vec<Diagnostic> {
+    let keys = RefCell::new(Keys { stack: vec![], warnings: vec![] });
+    let cb = |jev: Jev, data: Option<&Vec<u8>>, span: Span| {
+        keys.borrow_mut().event(jev, data, span);
+        Ok(())
+    };
+    let mut parser = init(config.recover_errors(true));
+    parser.errors = Some(vec![]);
+    let r = parse_buf(&mut parser, &cb, &mut inp);
+
+    let errors = parser.errors.take().unwrap_or_default();
+    let mut diags : Vec<Diagnostic> = errors.into_iter()
+        .map(|(kind, span)| Diagnostic { span, severity: Severity::Error, code: kind, message: kind.to_string() })
+        .collect();
+    // an error reading the input stops the validation
+    if let Err(mut e) = r {
+        let pos = e.pos.take().unwrap_or_else(|| parser.position());
+        diags.push(Diagnostic {
+            span: Span { start: pos, end: pos },
+            severity: Severity::Error,
+            code: e.kind(),
+            message: e.to_string(),
+        });
+    }
+    diags.extend(keys.into_inner().warnings);
+    diags.sort_by_key(|d| d.span.start.offset);
+    diags
+}
+
+#[cfg(test)]
+fn summary(config: Config, doc: &str) -> Vec<(usize, Severity, ErrorKind, String)> {
+    validate(config, doc.as_bytes()).into_iter()
+        .map(|d| (d.span.start.offset, d.severity, d.code, d.message))
+        .collect()
+}
+
+#[test]
+fn validate_reports_all_problems() {
+    let config = Config::default();
+    assert!(validate(config.clone(), &b"{\"a\": [1, 2], \"b\": {\"a\": null}}"[..]).is_empty());
+
+    let doc = "{\"a\": 1,\n \"a\": [1 2],\n \"b\": x,\n \"c\": \"\\q\"\n";
+    let error = |offset, kind: ErrorKind| (offset, Severity::Error, kind, kind.to_string());
+    assert!(summary(config.clone(), doc) == vec![
+        (10, Severity::Warning, ErrorKind::DuplicateKey, "duplicate key \"a\"".to_string()),
+        error(18, ErrorKind::UnexpectedChar),
+        error(28, ErrorKind::UnexpectedChar),
+        error(39, ErrorKind::UnexpectedChar),
+        error(42, ErrorKind::PrematureEof(Some(::StackMode::Object))),
+    ]);
+    let d = &validate(config.clone(), doc.as_bytes())[2];
+    assert!(d.span.start == ::pos(28, 3, 7) && d.span.end == ::pos(29, 3, 8));
+
+    // duplicates are per object and per document
+    let multi = config.multi_document(true);
+    assert!(validate(multi.clone(), &b"{\"a\":1}\n{\"a\":{\"a\":2}}"[..]).is_empty());
+    assert!(summary(multi, "{\"k\":1,\"k\":2}\n{\"k\":1,\"k\":2}").iter().map(|d| d.0).collect::<Vec<_>>() == vec![7, 21]);
+
+    // errors don't stop the tracking of the keys, in their object or the next record
+    let offsets = |config: Config, doc: &str| summary(config, doc).iter().map(|d| (d.0, d.1)).collect::<Vec<_>>();
+    assert!(offsets(Config::default(), "{\"a\": x, \"a\": [1}], \"a\": 2}")
+            == vec![(6, Severity::Error), (9, Severity::Warning), (16, Severity::Error), (20, Severity::Warning)]);
+    assert!(offsets(Config::default().json_seq(true), "\x1e{\"a\": [\n\x1e{\"a\":1,\"a\":2}\n")
+            == vec![(9, Severity::Error), (17, Severity::Warning)]);
+}
+
+#[test]
+fn validate_reports_read_errors() {
+    use std::io;
+    struct Failing;
+    impl Read for Failing {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("disk on fire"))
+        }
+    }
+    let diags = validate(Config::default(), Failing);
+    assert!(diags.len() == 1 && diags[0].code == ErrorKind::Io);
+    assert!(diags[0].message == "I/O error: disk on fire");
+}
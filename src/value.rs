@@ -0,0 +1,251 @@
+use std::io::{Read, Write};
+use std::str;
+use event::{parse_int, parse_uint};
+use {Config, ErrorKind, EventReader, JError, JResult, Jev, JsonWriter};
+
+/// JSON number, kept as written in the document
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Number(String);
+
+impl Number {
+    /// number from a float, None if the float is not finite
+    pub fn from_f64(f: f64) -> Option<Number> {
+        if f.is_finite() { Some(Number(format!("{:?}", f))) } else { None }
+    }
+
+    /// tell if the number is an integer, in decimal or JSON5 hexadecimal.
+    /// NaN and Infinity are not integers.
+    pub fn is_integer(&self) -> bool {
+        let unsigned = self.0.trim_start_matches(['-', '+']);
+        match unsigned.strip_prefix("0x").or_else(|| unsigned.strip_prefix("0X")) {
+            Some(hex) => !hex.is_empty() && hex.bytes().all(|c| c.is_ascii_hexdigit()),
+            None      => !unsigned.is_empty() && unsigned.bytes().all(|c| c.is_ascii_digit()),
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        if self.is_integer() { parse_int(&self.0) } else { None }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        if self.is_integer() { parse_uint(&self.0) } else { None }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        // hexadecimal integers are not understood by the float parsing
+        if let Some(i) = self.as_i64() {
+            Some(i as f64)
+        } else if let Some(u) = self.as_u64() {
+            Some(u as f64)
+        } else {
+            self.0.parse().ok()
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<i64> for Number {
+    fn from(i: i64) -> Number {
+        Number(i.to_string())
+    }
+}
+
+impl From<u64> for Number {
+    fn from(i: u64) -> Number {
+        Number(i.to_string())
+    }
+}
+
+/// in-memory JSON value. object keys are kept in the document order.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+/* nesting limit of the values read, when the config has none */
+const MAX_NESTING : usize = 128;
+
+/* container being built */
+enum Partial {
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>, String),
+}
+
+fn to_string(data: Option<&[u8]>) -> JResult<String> {
+    str::from_utf8(data.unwrap_or(&[]))
+        .map(|s| s.to_string())
+        .map_err(|_| JError::new(ErrorKind::Utf8))
+}
+
+impl Value {
+    /// build a value from a whole document read from a reader.
+    ///
+    /// in multi document mode, the input must contain a single document.
+    /// the nesting is limited to 128 levels unless the config sets another
+    /// limit, as writing and dropping the value recurse into it.
+    pub fn from_reader<R: Read>(mut config: Config, inp: R) -> JResult<Value> {
+        if config.max_nesting == 0 {
+            config.max_nesting = MAX_NESTING;
+        }
+        let mut reader = EventReader::new(config, inp);
+        let mut stack : Vec<Partial> = vec![];
+        let mut root = None;
+
+        while let Some(ev) = reader.next_raw()? {
+            let value = match ev.jev {
+                Jev::ArrayStart  => { stack.push(Partial::Array(vec![])); continue },
+                Jev::ObjectStart => { stack.push(Partial::Object(vec![], String::new())); continue },
+                Jev::Key         => {
+                    if let Some(&mut Partial::Object(_, ref mut key)) = stack.last_mut() {
+                        *key = to_string(ev.data)?;
+                    }
+                    continue
+                },
+                Jev::Comment | Jev::DocumentEnd => continue,
+                Jev::Error       => return Err(JError::custom(to_string(ev.data)?).at(ev.span.start)),
+                Jev::ArrayEnd | Jev::ObjectEnd => match stack.pop() {
+                    Some(Partial::Array(a))     => Value::Array(a),
+                    Some(Partial::Object(o, _)) => Value::Object(o),
+                    None                        => return Err(JError::new(ErrorKind::PopEmpty)),
+                },
+                Jev::String      => Value::String(to_string(ev.data)?),
+                Jev::Int | Jev::Float => Value::Number(Number(to_string(ev.data)?)),
+                Jev::True        => Value::Bool(true),
+                Jev::False       => Value::Bool(false),
+                Jev::Null        => Value::Null,
+            };
+            match stack.last_mut() {
+                None if root.is_some()               => return Err(JError::new(ErrorKind::UnexpectedEvent)),
+                None                                 => root = Some(value),
+                Some(&mut Partial::Array(ref mut a)) => a.push(value),
+                Some(&mut Partial::Object(ref mut o, ref mut key)) => o.push((key.split_off(0), value)),
+            }
+        }
+        root.ok_or_else(|| JError::new(ErrorKind::PrematureEof(None)))
+    }
+
+    /// build a value from a whole document in memory
+    pub fn from_slice(config: Config, data: &[u8]) -> JResult<Value> {
+        Value::from_reader(config, data)
+    }
+
+    /// write the value as events to a writer
+    pub fn write_to<W: Write>(&self, writer: &mut JsonWriter<W>) -> JResult<()> {
+        match *self {
+            Value::Null          => writer.write_event(Jev::Null, None),
+            Value::Bool(b)       => writer.write_event(if b { Jev::True } else { Jev::False }, None),
+            Value::Number(ref n) => {
+                let jev = if n.is_integer() { Jev::Int } else { Jev::Float };
+                writer.write_event(jev, Some(n.0.as_bytes()))
+            },
+            Value::String(ref s) => writer.write_event(Jev::String, Some(s.as_bytes())),
+            Value::Array(ref a)  => {
+                writer.write_event(Jev::ArrayStart, None)?;
+                for v in a {
+                    v.write_to(writer)?;
+                }
+                writer.write_event(Jev::ArrayEnd, None)
+            },
+            Value::Object(ref o) => {
+                writer.write_event(Jev::ObjectStart, None)?;
+                for (k, v) in o {
+                    writer.write_event(Jev::Key, Some(k.as_bytes()))?;
+                    v.write_to(writer)?;
+                }
+                writer.write_event(Jev::ObjectEnd, None)
+            },
+        }
+    }
+
+    /// value associated with the first occurrence of the key in an object
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match *self {
+            Value::Object(ref o) => o.iter().find(|e| e.0 == key).map(|e| &e.1),
+            _                    => None,
+        }
+    }
+}
+
+#[test]
+fn value_from_slice() {
+    let doc = b"{\"z\": [1, 2.5, \"s\", true, null], \"a\": {\"b\": false}, \"n\": -123456789012345678901234}";
+    let v = Value::from_slice(Config::default(), doc).ok().unwrap();
+    match v {
+        Value::Object(ref o) => assert!(o.iter().map(|e| &e.0[..]).collect::<Vec<_>>() == vec!["z", "a", "n"]),
+        _ => panic!("expected object"),
+    }
+    assert!(v.get("z") == Some(&Value::Array(vec![
+        Value::Number(Number::from(1i64)),
+        Value::Number(Number::from_f64(2.5).unwrap()),
+        Value::String("s".to_string()),
+        Value::Bool(true),
+        Value::Null,
+    ])));
+    assert!(v.get("a").and_then(|a| a.get("b")) == Some(&Value::Bool(false)));
+    match v.get("n") {
+        Some(Value::Number(n)) => {
+            assert!(n.as_i64().is_none());
+            assert!(n.as_str() == "-123456789012345678901234");
+        },
+        _ => panic!("expected number"),
+    }
+
+    let mut writer = JsonWriter::new(vec![]);
+    v.write_to(&mut writer).ok().unwrap();
+    writer.finish().ok().unwrap();
+    let out = writer.into_inner();
+    assert!(Value::from_slice(Config::default(), &out).ok() == Some(v));
+
+    // numbers outside of strict JSON need a writer accepting them
+    let v = Value::from_slice(Config::default().allow_json5(true), b"[Infinity, NaN, 0x1E, -.5, +2]").ok().unwrap();
+    let mut writer = JsonWriter::new(vec![]).allow_json5_numbers(true);
+    v.write_to(&mut writer).ok().unwrap();
+    assert!(writer.into_inner() == b"[Infinity,NaN,0x1E,-.5,+2]");
+    let mut writer = JsonWriter::new(vec![]);
+    assert!(v.write_to(&mut writer).err().map(|e| e.kind()) == Some(ErrorKind::InvalidValue));
+}
+
+#[test]
+fn json5_numbers() {
+    let v = Value::from_slice(Config::default().allow_json5(true), b"[0x1E, -0x1f, +7, 0xFFFFFFFFFFFFFFFF, 1e2, .5, Infinity]");
+    let numbers : Vec<Number> = match v.ok() {
+        Some(Value::Array(a)) => a.into_iter().map(|n| match n { Value::Number(n) => n, _ => panic!("expected number") }).collect(),
+        _ => panic!("expected array"),
+    };
+    let ints : Vec<(bool, Option<i64>, Option<u64>)> = numbers.iter().map(|n| (n.is_integer(), n.as_i64(), n.as_u64())).collect();
+    assert!(ints == vec![(true, Some(30), Some(30)), (true, Some(-31), None), (true, Some(7), Some(7)),
+                         (true, None, Some(u64::MAX)), (false, None, None), (false, None, None), (false, None, None)]);
+    let floats : Vec<Option<f64>> = numbers.iter().map(|n| n.as_f64()).collect();
+    assert!(floats == vec![Some(30.0), Some(-31.0), Some(7.0), Some(u64::MAX as f64), Some(100.0), Some(0.5), Some(f64::INFINITY)]);
+}
+
+#[test]
+fn value_respects_config() {
+    let config = Config::default().with_max_nesting(2);
+    assert!(Value::from_slice(config.clone(), b"[[]]").is_ok());
+    assert!(Value::from_slice(config, b"[[[]]]").err().map(|e| e.kind()) == Some(ErrorKind::NestingLimit));
+
+    // deep documents are refused by default, rather than overflowing the stack later
+    let deep = |n: usize| format!("{}{}", "[".repeat(n), "]".repeat(n));
+    assert!(Value::from_slice(Config::default(), deep(128).as_bytes()).is_ok());
+    assert!(Value::from_slice(Config::default(), deep(1_000_000).as_bytes()).err().map(|e| e.kind())
+            == Some(ErrorKind::NestingLimit));
+    let value = Value::from_slice(Config::default().with_max_nesting(200), deep(200).as_bytes()).unwrap();
+    let mut out = vec![];
+    value.write_to(&mut JsonWriter::new(&mut out)).unwrap();
+    assert!(out == deep(200).into_bytes());
+    assert!(Value::from_slice(Config::default(), b"").err().map(|e| e.kind()) == Some(ErrorKind::PrematureEof(None)));
+    assert!(Value::from_slice(Config::default(), b"[1").err().map(|e| e.kind())
+            == Some(ErrorKind::PrematureEof(Some(::StackMode::Array))));
+    let multi = Config::default().multi_document(true);
+    assert!(Value::from_slice(multi.clone(), b"[1]\n").ok() == Some(Value::Array(vec![Value::Number(Number::from(1i64))])));
+    assert!(Value::from_slice(multi, b"1\n2").err().map(|e| e.kind()) == Some(ErrorKind::UnexpectedEvent));
+}
@@ -0,0 +1,515 @@
+use std::io::{Read, Write};
+use std::mem;
+use std::str;
+use {ErrorKind, EventReader, JError, JResult, Jev, StackMode};
+
+/// output style of the writer
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Style {
+    /// no whitespace at all
+    Compact,
+    /// one element per line, indented by the given number of spaces per level
+    Spaces(usize),
+    /// one element per line, indented by one tab per level
+    Tabs,
+}
+
+/// streaming JSON writer, taking the same events as generated by the parser.
+///
+/// separators are inserted automatically, and the events are checked to
+/// form a single valid document.
+pub struct JsonWriter<W: Write> {
+    out: W,
+    style: Style,
+    stack: Vec<StackMode>,
+    first: bool,
+    expecting_key: bool,
+    done: bool,
+    comments: Vec<String>,
+    ascii: bool,
+    json5_numbers: bool,
+    documents: usize,
+}
+
+/* check the number against the JSON grammar: -?(0|[1-9][0-9]*)(.[0-9]+)?([eE][+-]?[0-9]+)? */
+fn is_number(d: &[u8], allow_real: bool) -> bool {
+    fn digits(d: &[u8], i: usize) -> usize {
+        d[i..].iter().take_while(|c| c.is_ascii_digit()).count()
+    }
+    let mut i = 0;
+    if d.first() == Some(&b'-') {
+        i += 1;
+    }
+    match d.get(i) {
+        Some(&b'0') => i += 1,
+        Some(c) if c.is_ascii_digit() => i += digits(d, i),
+        _ => return false,
+    }
+    if allow_real && d.get(i) == Some(&b'.') {
+        let n = digits(d, i + 1);
+        if n == 0 {
+            return false;
+        }
+        i += 1 + n;
+    }
+    if allow_real && (d.get(i) == Some(&b'e') || d.get(i) == Some(&b'E')) {
+        i += 1;
+        if d.get(i) == Some(&b'+') || d.get(i) == Some(&b'-') {
+            i += 1;
+        }
+        let n = digits(d, i);
+        if n == 0 {
+            return false;
+        }
+        i += n;
+    }
+    i == d.len()
+}
+
+/* check the number against the numbers of JSON5: hexadecimal integers, a
+ * leading + sign, a leading or trailing decimal point, Infinity and NaN */
+fn is_json5_number(d: &[u8], allow_real: bool) -> bool {
+    let unsigned = match d.first() {
+        Some(&b'+') | Some(&b'-') => &d[1..],
+        _ => d,
+    };
+    if unsigned.starts_with(b"0x") || unsigned.starts_with(b"0X") {
+        return unsigned.len() > 2 && unsigned[2..].iter().all(|c| c.is_ascii_hexdigit());
+    }
+    if !allow_real {
+        return is_number(unsigned, false);
+    }
+    if unsigned == b"Infinity" || unsigned == b"NaN" {
+        return true;
+    }
+    // .5 and 5. are 0.5 and 5.0 for the JSON grammar
+    let mut n = vec![];
+    for (i, &c) in unsigned.iter().enumerate() {
+        let digit_after = unsigned.get(i + 1).is_some_and(|c| c.is_ascii_digit());
+        if c == b'.' && i == 0 {
+            if !digit_after {
+                return false;
+            }
+            n.push(b'0');
+        }
+        n.push(c);
+        if c == b'.' && i > 0 && !digit_after {
+            n.push(b'0');
+        }
+    }
+    is_number(&n, true)
+}
+
+/* check the comment is either a complete C comment or a single line YAML comment */
+fn is_comment(s: &str) -> bool {
+    if let Some(body) = s.strip_prefix("/*") {
+        body.find("*/").map(|i| i + 2) == Some(body.len())
+    } else {
+        s.starts_with('#') && !s.contains('\n') && !s.contains('\r')
+    }
+}
+
+/// write a string with the JSON escaping, including the quotes.
+///
+/// with `ascii`, all non-ASCII characters are escaped too, using surrogate
+/// pairs for the characters outside of the basic multilingual plane.
+pub fn write_escaped<W: Write>(out: &mut W, s: &str, ascii: bool) -> JResult<()> {
+    let mut start = 0;
+    out.write_all(b"\"")?;
+    for (i, c) in s.char_indices() {
+        let esc : &[u8] = match c {
+            '"'  => b"\\\"",
+            '\\' => b"\\\\",
+            '\u{8}' => b"\\b",
+            '\u{c}' => b"\\f",
+            '\n' => b"\\n",
+            '\r' => b"\\r",
+            '\t' => b"\\t",
+            '\u{0}'..='\u{1f}' => b"",
+            _ if ascii && !c.is_ascii() => b"",
+            _    => continue,
+        };
+        out.write_all(&s.as_bytes()[start..i])?;
+        if esc.is_empty() {
+            let mut units = [0u16; 2];
+            for u in c.encode_utf16(&mut units) {
+                write!(out, "\\u{:04x}", u)?;
+            }
+        } else {
+            out.write_all(esc)?;
+        }
+        start = i + c.len_utf8();
+    }
+    out.write_all(&s.as_bytes()[start..])?;
+    out.write_all(b"\"")?;
+    Ok(())
+}
+
+fn err<T>(kind: ErrorKind) -> JResult<T> {
+    Err(JError::new(kind))
+}
+
+impl<W: Write> JsonWriter<W> {
+    pub fn new(out: W) -> JsonWriter<W> {
+        JsonWriter::with_style(out, Style::Compact)
+    }
+
+    pub fn with_style(out: W, style: Style) -> JsonWriter<W> {
+        JsonWriter {
+            out,
+            style,
+            stack: vec![],
+            first: true,
+            expecting_key: false,
+            done: false,
+            comments: vec![],
+            ascii: false,
+            json5_numbers: false,
+            documents: 0,
+        }
+    }
+
+    /// escape all non-ASCII characters in strings and keys
+    pub fn escape_non_ascii(mut self, escape: bool) -> JsonWriter<W> {
+        self.ascii = escape;
+        self
+    }
+
+    /// accept the numbers of JSON5 and `Config::allow_nan_infinity`, written
+    /// as given: NaN, Infinity, hexadecimal integers, a leading + sign, a
+    /// leading or trailing decimal point. the output is then no longer
+    /// strict JSON, which is otherwise the only output accepted.
+    pub fn allow_json5_numbers(mut self, allow: bool) -> JsonWriter<W> {
+        self.json5_numbers = allow;
+        self
+    }
+
+    /// write the next event, with its data for keys, strings and numbers
+    pub fn write_event(&mut self, jev: Jev, data: Option<&[u8]>) -> JResult<()> {
+        match jev {
+            Jev::ArrayEnd  => self.close(StackMode::Array),
+            Jev::ObjectEnd => self.close(StackMode::Object),
+            Jev::Key       => {
+                let s = as_str(data)?;
+                if self.stack.last() != Some(&StackMode::Object) || !self.expecting_key {
+                    return err(ErrorKind::UnexpectedEvent);
+                }
+                self.element()?;
+                write_escaped(&mut self.out, s, self.ascii)?;
+                self.out.write_all(if self.style == Style::Compact { b":" } else { b": " })?;
+                self.first = false;
+                self.expecting_key = false;
+                Ok(())
+            },
+            Jev::String    => {
+                let s = as_str(data)?;
+                self.value()?;
+                write_escaped(&mut self.out, s, self.ascii)?;
+                self.end_value();
+                Ok(())
+            },
+            Jev::Int | Jev::Float => {
+                let d = data.unwrap_or(&[]);
+                let valid =
+                    if self.json5_numbers {
+                        is_json5_number(d, jev == Jev::Float)
+                    } else {
+                        is_number(d, jev == Jev::Float)
+                    };
+                if !valid {
+                    return err(ErrorKind::InvalidValue);
+                }
+                self.literal(d)
+            },
+            Jev::True  => self.literal(b"true"),
+            Jev::False => self.literal(b"false"),
+            Jev::Null  => self.literal(b"null"),
+            Jev::Comment => {
+                let s = as_str(data)?;
+                if !is_comment(s) {
+                    return err(ErrorKind::InvalidValue);
+                }
+                self.comments.push(s.to_string());
+                Ok(())
+            },
+            Jev::Error => err(ErrorKind::UnexpectedEvent),
+            Jev::DocumentEnd => {
+                if !self.done {
+                    return err(ErrorKind::UnexpectedEvent);
+                }
+                self.out.write_all(b"\n")?;
+                self.done = false;
+                self.first = true;
+                self.documents += 1;
+                Ok(())
+            },
+            Jev::ArrayStart | Jev::ObjectStart => {
+                self.value()?;
+                if jev == Jev::ArrayStart {
+                    self.out.write_all(b"[")?;
+                    self.stack.push(StackMode::Array);
+                } else {
+                    self.out.write_all(b"{")?;
+                    self.stack.push(StackMode::Object);
+                    self.expecting_key = true;
+                }
+                self.first = true;
+                Ok(())
+            },
+        }
+    }
+
+    /// check that the document is complete and flush the output.
+    ///
+    /// after `Jev::DocumentEnd` events, each document is on its own line and
+    /// the output may end right after the last one.
+    pub fn finish(&mut self) -> JResult<()> {
+        if !self.done && (self.documents == 0 || !self.stack.is_empty()) {
+            return err(ErrorKind::PrematureEof(self.stack.last().cloned()));
+        }
+        if self.done && self.style != Style::Compact {
+            self.out.write_all(b"\n")?;
+        }
+        self.comments_inline(0)?;
+        self.out.flush()?;
+        Ok(())
+    }
+
+    /// return the underlying writer
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    fn literal(&mut self, d: &[u8]) -> JResult<()> {
+        self.value()?;
+        self.out.write_all(d)?;
+        self.end_value();
+        Ok(())
+    }
+
+    fn close(&mut self, mode: StackMode) -> JResult<()> {
+        match self.stack.last() {
+            None => return err(ErrorKind::PopEmpty),
+            Some(&m) if m != mode => return err(ErrorKind::PopUnexpectedMode),
+            Some(&StackMode::Object) if !self.expecting_key => return err(ErrorKind::UnexpectedEvent),
+            _ => (),
+        }
+        let depth = self.stack.len();
+        let empty = self.first && self.comments.is_empty();
+        self.comments_lines(depth)?;
+        if !empty {
+            self.newline(depth - 1)?;
+        }
+        self.stack.pop();
+        self.out.write_all(if mode == StackMode::Array { b"]" } else { b"}" })?;
+        self.end_value();
+        Ok(())
+    }
+
+    /* check a value is allowed here, and write what goes before it */
+    fn value(&mut self) -> JResult<()> {
+        match self.stack.last() {
+            None if self.done => err(ErrorKind::UnexpectedEvent),
+            None => self.comments_inline(0),
+            Some(&StackMode::Object) if self.expecting_key => err(ErrorKind::UnexpectedEvent),
+            Some(&StackMode::Object) => {
+                let depth = self.stack.len();
+                self.comments_inline(depth)
+            },
+            Some(&StackMode::Array) => self.element(),
+        }
+    }
+
+    /* start a new element of the current container: array value or object key */
+    fn element(&mut self) -> JResult<()> {
+        if !self.first {
+            self.out.write_all(b",")?;
+        }
+        let depth = self.stack.len();
+        self.comments_lines(depth)?;
+        self.newline(depth)
+    }
+
+    fn newline(&mut self, depth: usize) -> JResult<()> {
+        match self.style {
+            Style::Compact => return Ok(()),
+            Style::Spaces(n) => write!(self.out, "\n{:1$}", "", n * depth)?,
+            Style::Tabs => write!(self.out, "\n{}", "\t".repeat(depth))?,
+        }
+        Ok(())
+    }
+
+    /* write the pending comments each on its own line */
+    fn comments_lines(&mut self, depth: usize) -> JResult<()> {
+        for c in mem::take(&mut self.comments) {
+            self.newline(depth)?;
+            self.out.write_all(c.as_bytes())?;
+            if self.style == Style::Compact && c.starts_with('#') {
+                self.out.write_all(b"\n")?;
+            }
+        }
+        Ok(())
+    }
+
+    /* write the pending comments in front of the next token */
+    fn comments_inline(&mut self, depth: usize) -> JResult<()> {
+        for c in mem::take(&mut self.comments) {
+            self.out.write_all(c.as_bytes())?;
+            if c.starts_with('#') {
+                if self.style == Style::Compact {
+                    self.out.write_all(b"\n")?;
+                } else {
+                    self.newline(depth)?;
+                }
+            } else if self.style != Style::Compact {
+                self.out.write_all(b" ")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn end_value(&mut self) {
+        self.first = false;
+        match self.stack.last() {
+            None                     => self.done = true,
+            Some(&StackMode::Object) => self.expecting_key = true,
+            Some(&StackMode::Array)  => (),
+        }
+    }
+}
+
+/// reformat a document from a reader with the given style.
+///
+/// the events are streamed from the reader to the output, so any size of
+/// document can be reformatted in constant memory. comments are kept when
+/// the reader's configuration emits them, and so are the numbers of JSON5
+/// and `Config::allow_nan_infinity` when it accepts them.
+pub fn reformat<R: Read, W: Write>(reader: &mut EventReader<R>, out: W, style: Style) -> JResult<W> {
+    let config = reader.config();
+    let json5_numbers = config.extension(config.allow_json5 || config.allow_nan_infinity);
+    let mut writer = JsonWriter::with_style(out, style).allow_json5_numbers(json5_numbers);
+    while let Some(ev) = reader.next_raw()? {
+        writer.write_event(ev.jev, ev.data)?;
+    }
+    writer.finish()?;
+    Ok(writer.into_inner())
+}
+
+fn as_str(data: Option<&[u8]>) -> JResult<&str> {
+    match data {
+        None    => err(ErrorKind::InvalidValue),
+        Some(d) => str::from_utf8(d).map_err(|_| JError::new(ErrorKind::Utf8)),
+    }
+}
+
+#[cfg(test)]
+fn write_all(events: &[(Jev, Option<&[u8]>)]) -> JResult<String> {
+    let mut w = JsonWriter::new(vec![]);
+    for &(jev, data) in events {
+        w.write_event(jev, data)?;
+    }
+    w.finish()?;
+    Ok(String::from_utf8(w.into_inner()).unwrap())
+}
+
+#[test]
+fn writer_roundtrip() {
+    let doc = "{\"a\":[1,-2.5e3,\"x y\",true,false,null,{}],\"b\":{\"c\":[]}}";
+    let events = ::collect_chunks(&[doc.as_bytes()]);
+    let events : Vec<(Jev, Option<&[u8]>)> = events.iter().map(|&(j, ref d)| (j, d.as_ref().map(|d| &d[..]))).collect();
+    assert!(write_all(&events).ok().unwrap() == doc);
+}
+
+#[test]
+fn writer_escapes() {
+    let mut out = vec![];
+    write_escaped(&mut out, "a\"b\\c\u{8}\u{c}\n\r\t\u{1}\u{1f}é/", false).ok().unwrap();
+    assert!(out == b"\"a\\\"b\\\\c\\b\\f\\n\\r\\t\\u0001\\u001f\xc3\xa9/\"".to_vec());
+    let mut out = vec![];
+    write_escaped(&mut out, "é☃😀\u{7f}", true).ok().unwrap();
+    assert!(out == b"\"\\u00e9\\u2603\\ud83d\\ude00\x7f\"".to_vec());
+}
+
+#[test]
+fn writer_misuse() {
+    fn kind(events: &[(Jev, Option<&[u8]>)]) -> Option<ErrorKind> {
+        write_all(events).err().map(|e| e.kind())
+    }
+    assert!(kind(&[(Jev::ObjectStart, None), (Jev::Int, Some(b"1"))]) == Some(ErrorKind::UnexpectedEvent));
+    assert!(kind(&[(Jev::ArrayStart, None), (Jev::Key, Some(b"k"))]) == Some(ErrorKind::UnexpectedEvent));
+    assert!(kind(&[(Jev::ObjectStart, None), (Jev::Key, Some(b"k")), (Jev::ObjectEnd, None)])
+            == Some(ErrorKind::UnexpectedEvent));
+    assert!(kind(&[(Jev::ArrayStart, None), (Jev::ObjectEnd, None)]) == Some(ErrorKind::PopUnexpectedMode));
+    assert!(kind(&[(Jev::ArrayEnd, None)]) == Some(ErrorKind::PopEmpty));
+    assert!(kind(&[(Jev::Null, None), (Jev::Null, None)]) == Some(ErrorKind::UnexpectedEvent));
+    assert!(kind(&[(Jev::ArrayStart, None)]) == Some(ErrorKind::PrematureEof(Some(StackMode::Array))));
+    assert!(kind(&[(Jev::Int, Some(b"1.5"))]) == Some(ErrorKind::InvalidValue));
+    assert!(kind(&[(Jev::Float, Some(b"01"))]) == Some(ErrorKind::InvalidValue));
+    assert!(kind(&[(Jev::Float, Some(b"NaN"))]) == Some(ErrorKind::InvalidValue));
+    assert!(kind(&[(Jev::Int, Some(b"0x1F"))]) == Some(ErrorKind::InvalidValue));
+    assert!(kind(&[(Jev::Int, Some(b"+1"))]) == Some(ErrorKind::InvalidValue));
+    assert!(kind(&[(Jev::String, None)]) == Some(ErrorKind::InvalidValue));
+    assert!(kind(&[(Jev::DocumentEnd, Some(b"0"))]) == Some(ErrorKind::UnexpectedEvent));
+    assert!(kind(&[(Jev::Null, None), (Jev::DocumentEnd, Some(b"0")), (Jev::ArrayStart, None)])
+            == Some(ErrorKind::PrematureEof(Some(StackMode::Array))));
+}
+
+#[test]
+fn writer_json5_numbers() {
+    let numbers : &[(Jev, &[u8])] = &[(Jev::Int, b"0x1F"), (Jev::Int, b"-0XaB"), (Jev::Int, b"+1"), (Jev::Float, b".5"),
+                                      (Jev::Float, b"5."), (Jev::Float, b"-.5e1"), (Jev::Float, b"Infinity"),
+                                      (Jev::Float, b"-Infinity"), (Jev::Float, b"+NaN"), (Jev::Float, b"1.5")];
+    for &(jev, d) in numbers {
+        let mut w = JsonWriter::new(vec![]).allow_json5_numbers(true);
+        assert!(w.write_event(jev, Some(d)).is_ok());
+        assert!(w.into_inner() == d);
+    }
+    let invalid : &[(Jev, &[u8])] = &[(Jev::Int, b"0x"), (Jev::Int, b"0xg"), (Jev::Int, b"1.5"), (Jev::Int, b"NaN"),
+                                      (Jev::Float, b"."), (Jev::Float, b".e1"), (Jev::Float, b"++1"), (Jev::Float, b"inf"), (Jev::Float, b"01")];
+    for &(jev, d) in invalid {
+        let mut w = JsonWriter::new(vec![]).allow_json5_numbers(true);
+        assert!(w.write_event(jev, Some(d)).err().map(|e| e.kind()) == Some(ErrorKind::InvalidValue));
+    }
+}
+
+#[cfg(test)]
+fn reformat_str(doc: &str, config: ::Config, style: Style) -> String {
+    let mut reader = EventReader::new(config, doc.as_bytes());
+    String::from_utf8(reformat(&mut reader, vec![], style).ok().unwrap()).unwrap()
+}
+
+#[test]
+fn reformat_styles() {
+    let doc = " { \"a\" : [ 1 , { } , [ ] , {\"b\":null} ] , \"c\" : \"d\" } ";
+    let config = ::Config::default();
+    assert!(reformat_str(doc, config.clone(), Style::Compact) == "{\"a\":[1,{},[],{\"b\":null}],\"c\":\"d\"}");
+    assert!(reformat_str(doc, config.clone(), Style::Spaces(2)) ==
+            "{\n  \"a\": [\n    1,\n    {},\n    [],\n    {\n      \"b\": null\n    }\n  ],\n  \"c\": \"d\"\n}\n");
+    assert!(reformat_str("[1,[2]]", config, Style::Tabs) == "[\n\t1,\n\t[\n\t\t2\n\t]\n]\n");
+}
+
+#[test]
+fn reformat_keeps_comments() {
+    let config = ::Config::default().allow_c_comments(true).allow_yaml_comments(true).emit_comments(true);
+    let doc = "# head\n[1, /* one */ 2 # two\n, {\"k\": /* v */ 3}]";
+    assert!(reformat_str(doc, config.clone(), Style::Compact) == "# head\n[1,/* one */2,# two\n{\"k\":/* v */3}]");
+    assert!(reformat_str(doc, config.clone(), Style::Spaces(2)) ==
+            "# head\n[\n  1,\n  /* one */\n  2,\n  # two\n  {\n    \"k\": /* v */ 3\n  }\n]\n");
+    let config = config.emit_comments(false);
+    assert!(reformat_str(doc, config, Style::Compact) == "[1,2,{\"k\":3}]");
+}
+
+#[test]
+fn reformat_documents() {
+    let config = ::Config::default().multi_document(true);
+    let doc = "{ \"a\" : 1 }\n\n[ 2 ] 3\n\"x\"";
+    assert!(reformat_str(doc, config.clone(), Style::Compact) == "{\"a\":1}\n[2]\n3\n\"x\"\n");
+    assert!(reformat_str("[1] [2]", config, Style::Spaces(2)) == "[\n  1\n]\n[\n  2\n]\n");
+}
+
+#[test]
+fn reformat_json5_numbers() {
+    let doc = "[0x1F, +1, .5, 5., NaN, -Infinity]";
+    assert!(reformat_str(doc, ::Config::default().allow_json5(true), Style::Compact) == "[0x1F,+1,.5,5.,NaN,-Infinity]");
+    assert!(reformat_str("[NaN, Infinity]", ::Config::default().allow_nan_infinity(true), Style::Compact) == "[NaN,Infinity]");
+}
@@ -0,0 +1,59 @@
+//! conformance harness running the JSONTestSuite style cases vendored in
+//! `tests/jsontestsuite`.
+//!
+//! the name of each case tells the expected result: `y_` must be accepted,
+//! `n_` must be rejected, and `i_` is implementation defined, so the result
+//! is only reported. run with `--nocapture` to see the result of each case.
+
+extern crate json_events;
+
+use std::fs;
+use std::path::Path;
+use json_events::{init, Config, JError, Jev, Span};
+
+fn accepts(config: Config, doc: &[u8]) -> bool {
+    let cb = |_: Jev, _: Option<&Vec<u8>>, _: Span| -> Result<(), JError> { Ok(()) };
+    let mut parser = init(config);
+    parser.feed(&cb, doc).and_then(|_| parser.finish(&cb)).is_ok()
+}
+
+fn cases() -> Vec<(String, Vec<u8>)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("jsontestsuite");
+    let mut cases : Vec<(String, Vec<u8>)> = fs::read_dir(dir).unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension().map(|e| e == "json").unwrap_or(false))
+        .map(|p| (p.file_stem().unwrap().to_string_lossy().into_owned(), fs::read(&p).unwrap()))
+        .collect();
+    cases.sort();
+    cases
+}
+
+fn outcome(accepted: bool) -> &'static str {
+    if accepted { "accepted" } else { "rejected" }
+}
+
+#[test]
+fn jsontestsuite() {
+    let cases = cases();
+    assert!(!cases.is_empty());
+    let mut failures = vec![];
+    for (name, doc) in &cases {
+        let strict = accepts(Config::default().strict(true), doc);
+        let lenient = accepts(Config::default(), doc);
+        let pass =
+            if name.starts_with("y_") {
+                strict && lenient
+            } else if name.starts_with("n_") {
+                !strict
+            } else {
+                true
+            };
+        println!("{} {:<50} strict: {}, default: {}",
+                 if pass { "PASS" } else { "FAIL" }, name, outcome(strict), outcome(lenient));
+        if !pass {
+            failures.push(name.clone());
+        }
+    }
+    println!("{} cases, {} failures", cases.len(), failures.len());
+    assert!(failures.is_empty(), "failing cases: {:?}", failures);
+}